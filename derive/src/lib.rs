@@ -0,0 +1,238 @@
+//! Derive macros for [unleash-api-client](https://docs.rs/unleash-api-client).
+//!
+//! Use them through the `derive` feature of that crate rather than depending
+//! on this crate directly.
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitBool, LitStr};
+
+/// Derive the traits a feature enum needs to be used with a `Client`.
+///
+/// Implements `Clone`, `Copy`, `Debug`, `enum_map::Enum`,
+/// `enum_map::EnumArray` and serde's `Serialize` and `Deserialize`, all in
+/// terms of the toggle name of each variant, plus `FeatureDefaults` and an
+/// inherent `name` method. Do not derive or implement any of those traits as
+/// well.
+///
+/// Each variant maps to the toggle with the same name unless renamed:
+///
+/// ```ignore
+/// use unleash_api_client::FeatureEnum;
+///
+/// #[derive(FeatureEnum)]
+/// enum Features {
+///     #[unleash(name = "checkout.new-flow")]
+///     NewCheckout,
+///     #[unleash(name = "search", default = true, default_variant = "classic")]
+///     Search,
+/// }
+/// ```
+///
+/// `default` and `default_variant` set what `FeatureDefaults` returns for the
+/// variant, for when the toggle is unknown to the server: by default it is
+/// disabled, with the disabled variant.
+#[proc_macro_derive(FeatureEnum, attributes(unleash))]
+pub fn derive_feature_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Variant {
+    ident: syn::Ident,
+    name: String,
+    default: bool,
+    default_variant: Option<String>,
+}
+
+fn parse_variant(variant: &syn::Variant) -> syn::Result<Variant> {
+    if !matches!(variant.fields, Fields::Unit) {
+        return Err(Error::new_spanned(
+            variant,
+            "FeatureEnum variants cannot have fields",
+        ));
+    }
+    let mut parsed = Variant {
+        ident: variant.ident.clone(),
+        name: variant.ident.to_string(),
+        default: false,
+        default_variant: None,
+    };
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("unleash"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                parsed.name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                parsed.default = meta.value()?.parse::<LitBool>()?.value;
+                Ok(())
+            } else if meta.path.is_ident("default_variant") {
+                parsed.default_variant = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `name`, `default` or `default_variant`"))
+            }
+        })?;
+    }
+    Ok(parsed)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "FeatureEnum can only be derived for enums",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "FeatureEnum cannot be derived for generic enums",
+        ));
+    }
+    let variants = data
+        .variants
+        .iter()
+        .map(parse_variant)
+        .collect::<syn::Result<Vec<_>>>()?;
+    for (i, variant) in variants.iter().enumerate() {
+        if variants[..i].iter().any(|other| other.name == variant.name) {
+            return Err(Error::new_spanned(
+                &variant.ident,
+                format!("duplicate toggle name {:?}", variant.name),
+            ));
+        }
+    }
+
+    let ident = &input.ident;
+    let enum_name = ident.to_string();
+    let private = quote!(::unleash_api_client::__private);
+    let length = variants.len();
+    let idents: Vec<_> = variants.iter().map(|v| &v.ident).collect();
+    let names: Vec<_> = variants.iter().map(|v| &v.name).collect();
+    let defaults: Vec<_> = variants.iter().map(|v| v.default).collect();
+    let default_variants: Vec<_> = variants
+        .iter()
+        .map(|v| match &v.default_variant {
+            Some(name) => quote! {
+                ::unleash_api_client::client::Variant {
+                    name: #name.into(),
+                    enabled: true,
+                    ..::core::default::Default::default()
+                }
+            },
+            None => quote!(::unleash_api_client::client::Variant::disabled()),
+        })
+        .collect();
+    let indices: Vec<_> = (0..length).collect();
+    let indices_u32: Vec<_> = (0..length as u32).collect();
+
+    Ok(quote! {
+        impl #ident {
+            /// The name of the toggle this variant maps to.
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#idents => #names,)*
+                }
+            }
+        }
+
+        impl ::unleash_api_client::client::FeatureDefaults for #ident {
+            fn default_enabled(&self) -> bool {
+                match self {
+                    #(Self::#idents => #defaults,)*
+                }
+            }
+
+            fn default_variant(&self) -> ::unleash_api_client::client::Variant {
+                match self {
+                    #(Self::#idents => #default_variants,)*
+                }
+            }
+        }
+
+        impl ::core::clone::Clone for #ident {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl ::core::marker::Copy for #ident {}
+
+        impl ::core::fmt::Debug for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl #private::enum_map::Enum for #ident {
+            const LENGTH: usize = #length;
+
+            fn from_usize(value: usize) -> Self {
+                match value {
+                    #(#indices => Self::#idents,)*
+                    _ => panic!("index {} out of range for {}", value, #enum_name),
+                }
+            }
+
+            fn into_usize(self) -> usize {
+                match self {
+                    #(Self::#idents => #indices,)*
+                }
+            }
+        }
+
+        impl<V> #private::enum_map::EnumArray<V> for #ident {
+            type Array = [V; #length];
+        }
+
+        impl #private::serde::Serialize for #ident {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: #private::serde::Serializer,
+            {
+                match self {
+                    #(Self::#idents => serializer.serialize_unit_variant(#enum_name, #indices_u32, #names),)*
+                }
+            }
+        }
+
+        impl<'de> #private::serde::Deserialize<'de> for #ident {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: #private::serde::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> #private::serde::de::Visitor<'de> for Visitor {
+                    type Value = #ident;
+
+                    fn expecting(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        f.write_str(concat!("a toggle name of ", #enum_name))
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> ::core::result::Result<#ident, E>
+                    where
+                        E: #private::serde::de::Error,
+                    {
+                        match value {
+                            #(#names => Ok(#ident::#idents),)*
+                            _ => Err(E::unknown_variant(value, &[#(#names),*])),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_str(Visitor)
+            }
+        }
+    })
+}
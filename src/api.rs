@@ -2,6 +2,7 @@
 //! <https://docs.getunleash.io/api/client/features>
 use std::collections::HashMap;
 use std::default::Default;
+use std::fmt;
 
 use crate::version::get_sdk_version;
 use chrono::{DateTime, Utc};
@@ -55,6 +56,38 @@ pub struct Constraint {
     pub expression: ConstraintExpression,
 }
 
+/// Renders as e.g. `userId NOT IN [alice, bob]`.
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ConstraintExpression::*;
+        let list = |values: &[String]| format!("[{}]", values.join(", "));
+        let (operator, operand) = match &self.expression {
+            DateAfter { value } => ("DATE_AFTER", value.to_rfc3339()),
+            DateBefore { value } => ("DATE_BEFORE", value.to_rfc3339()),
+            In { values } => ("IN", list(values)),
+            NotIn { values } => ("NOT_IN", list(values)),
+            NumEq { value } => ("NUM_EQ", value.to_string()),
+            NumGT { value } => ("NUM_GT", value.to_string()),
+            NumGTE { value } => ("NUM_GTE", value.to_string()),
+            NumLT { value } => ("NUM_LT", value.to_string()),
+            NumLTE { value } => ("NUM_LTE", value.to_string()),
+            SemverEq { value } => ("SEMVER_EQ", value.to_string()),
+            SemverGT { value } => ("SEMVER_GT", value.to_string()),
+            SemverLT { value } => ("SEMVER_LT", value.to_string()),
+            StrContains { values } => ("STR_CONTAINS", list(values)),
+            StrStartsWith { values } => ("STR_STARTS_WITH", list(values)),
+            StrEndsWith { values } => ("STR_ENDS_WITH", list(values)),
+            Unknown(value) => ("UNKNOWN", value.to_string()),
+        };
+        let inverted = if self.inverted { "NOT " } else { "" };
+        write!(f, "{} {inverted}{operator} {operand}", self.context_name)?;
+        if self.case_insensitive {
+            f.write_str(" (case insensitive)")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(tag = "operator")]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -199,10 +232,11 @@ impl Metrics {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
 pub struct ToggleMetrics {
     pub yes: u64,
     pub no: u64,
+    #[serde(default)]
     pub variants: HashMap<String, u64>,
 }
 
@@ -213,6 +247,63 @@ pub struct MetricsBucket {
     pub toggles: HashMap<String, ToggleMetrics>,
 }
 
+/// <https://docs.getunleash.io/reference/front-end-api>
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FrontendFeatures {
+    pub toggles: Vec<FrontendToggle>,
+}
+
+impl FrontendFeatures {
+    pub fn endpoint(api_url: &str) -> String {
+        format!("{}/frontend", api_url.trim_end_matches('/'))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FrontendToggle {
+    pub name: String,
+    pub enabled: bool,
+    pub variant: FrontendVariant,
+    #[serde(rename = "impressionData", default)]
+    pub impression_data: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FrontendVariant {
+    pub name: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub feature_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<FrontendPayload>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FrontendPayload {
+    #[serde(rename = "type")]
+    pub _type: String,
+    pub value: String,
+}
+
+/// Metrics as posted by frontend SDKs; unlike `Metrics` there is no
+/// connection id.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FrontendMetrics {
+    #[serde(rename = "appName")]
+    pub app_name: String,
+    #[serde(rename = "instanceId", default)]
+    pub instance_id: Option<String>,
+    #[serde(default)]
+    pub environment: Option<String>,
+    pub bucket: MetricsBucket,
+}
+
+impl FrontendMetrics {
+    pub fn endpoint(api_url: &str) -> String {
+        format!("{}/frontend/client/metrics", api_url.trim_end_matches('/'))
+    }
+}
+
 fn deserialize_number_from_string<'de, T, D>(deserializer: D) -> Result<T, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -493,4 +584,21 @@ mod tests {
             "https://localhost:4242/api/client/metrics"
         );
     }
+
+    #[test]
+    fn test_display_constraint() -> Result<(), serde_json::Error> {
+        let constraint: Constraint = serde_json::from_str(
+            r#"{"contextName": "userId", "operator": "IN", "values": ["alice", "bob"],
+                "inverted": true, "caseInsensitive": true}"#,
+        )?;
+        assert_eq!(
+            constraint.to_string(),
+            "userId NOT IN [alice, bob] (case insensitive)"
+        );
+        let constraint: Constraint = serde_json::from_str(
+            r#"{"contextName": "version", "operator": "SEMVER_GT", "value": "1.2.3"}"#,
+        )?;
+        assert_eq!(constraint.to_string(), "version SEMVER_GT 1.2.3");
+        Ok(())
+    }
 }
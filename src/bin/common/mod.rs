@@ -0,0 +1,33 @@
+//! Loading toggles, shared by the binaries.
+use std::fs;
+
+use tokio::runtime;
+use uuid::Uuid;
+
+use unleash_api_client::api;
+use unleash_api_client::config::EnvironmentConfig;
+use unleash_api_client::http;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Read toggles from a features export at `file`, or fetch them from the
+/// server configured in the environment (see `EnvironmentConfig`).
+pub fn load(file: Option<&str>) -> Result<api::Features, Error> {
+    if let Some(path) = file {
+        return Ok(serde_json::from_str(&fs::read_to_string(path)?)?);
+    }
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let config = EnvironmentConfig::from_env()?;
+            let endpoint = api::Features::endpoint(&config.api_url);
+            let client: http::HTTP<reqwest::Client> = http::HTTP::new(
+                config.app_name,
+                config.instance_id,
+                Uuid::new_v4().to_string(),
+                config.secret,
+            )?;
+            Ok(client.get(&endpoint).send().await?.json().await?)
+        })
+}
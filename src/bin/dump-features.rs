@@ -1,23 +1,269 @@
 // Copyright 2020 Cognite AS
-use async_std::task;
-use uuid::Uuid;
-
-use unleash_api_client::api;
-use unleash_api_client::config::EnvironmentConfig;
-use unleash_api_client::http;
-
-fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-    task::block_on(async {
-        let config = EnvironmentConfig::from_env()?;
-        let endpoint = api::Features::endpoint(&config.api_url);
-        let client: http::HTTP<reqwest::Client> = http::HTTP::new(
-            config.app_name,
-            config.instance_id,
-            Uuid::new_v4().to_string(),
-            config.secret,
-        )?;
-        let res: api::Features = client.get(&endpoint).send().await?.json().await?;
-        dbg!(res);
-        Ok(())
-    })
+//! Inspect the toggles of an Unleash server or a features export file.
+//!
+//! Toggles are read from `--file` if given, otherwise fetched from the server
+//! configured in the environment (see `EnvironmentConfig`).
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use unleash_api_client::api::Feature;
+use unleash_api_client::context::IPAddress;
+use unleash_api_client::Context;
+
+use common::{load, Error};
+use evaluator::Evaluator;
+
+mod common;
+mod evaluator;
+
+#[derive(Parser)]
+#[command(about = "Inspect Unleash feature toggles")]
+struct Cli {
+    /// Read toggles from a features export instead of the API.
+    #[arg(long, global = true)]
+    file: Option<String>,
+    /// Output format.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Yaml,
+    Table,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List all features.
+    List,
+    /// Show one feature's strategies, constraints and variants.
+    Show {
+        /// The feature name.
+        name: String,
+    },
+    /// Evaluate one feature for a context.
+    Eval {
+        /// The feature name.
+        name: String,
+        #[command(flatten)]
+        context: ContextArgs,
+    },
+}
+
+#[derive(Args)]
+struct ContextArgs {
+    /// A context as JSON, e.g. '{"userId": "fred"}'; the other flags
+    /// override its fields.
+    #[arg(long)]
+    context: Option<String>,
+    #[arg(long)]
+    user_id: Option<String>,
+    #[arg(long)]
+    session_id: Option<String>,
+    #[arg(long)]
+    remote_address: Option<IpAddr>,
+    #[arg(long)]
+    app_name: Option<String>,
+    #[arg(long)]
+    environment: Option<String>,
+    /// A context property, as NAME=VALUE. May be repeated.
+    #[arg(long = "property", value_parser = parse_property)]
+    properties: Vec<(String, String)>,
+}
+
+impl ContextArgs {
+    fn into_context(self) -> Result<Context, Error> {
+        let mut context: Context = match self.context {
+            Some(json) => serde_json::from_str(&json)?,
+            None => Context::default(),
+        };
+        if self.user_id.is_some() {
+            context.user_id = self.user_id;
+        }
+        if self.session_id.is_some() {
+            context.session_id = self.session_id;
+        }
+        if let Some(addr) = self.remote_address {
+            context.remote_address = Some(IPAddress(addr));
+        }
+        if let Some(app_name) = self.app_name {
+            context.app_name = app_name;
+        }
+        if let Some(environment) = self.environment {
+            context.environment = environment;
+        }
+        for (name, value) in self.properties {
+            context.properties.insert(name, value.into());
+        }
+        Ok(context)
+    }
+}
+
+fn parse_property(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {arg:?}"))
+}
+
+#[derive(Serialize)]
+struct FeatureSummary<'a> {
+    name: &'a str,
+    enabled: bool,
+    strategies: usize,
+    variants: usize,
+    description: &'a str,
+}
+
+#[derive(Serialize)]
+struct Evaluation {
+    name: String,
+    enabled: bool,
+    variant: String,
+    payload: HashMap<String, String>,
+    /// Why, from the explanation of the evaluation.
+    reason: String,
+}
+
+fn emit<T: Serialize>(
+    format: Format,
+    value: &T,
+    table: impl FnOnce() -> String,
+) -> Result<(), Error> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Format::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        Format::Table => print!("{}", table()),
+    }
+    Ok(())
+}
+
+/// Left aligned columns, separated by two spaces.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        format!("{}\n", cells.join("  ").trim_end())
+    };
+    let mut out = line(headers.to_vec());
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+fn show_table(feature: &Feature) -> String {
+    let mut out = format!(
+        "{} ({})\n",
+        feature.name,
+        if feature.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    if let Some(description) = &feature.description {
+        out.push_str(&format!("  {description}\n"));
+    }
+    for strategy in &feature.strategies {
+        out.push_str(&format!("strategy {}\n", strategy.name));
+        let mut parameters: Vec<_> = strategy.parameters.iter().flatten().collect();
+        parameters.sort();
+        for (name, value) in parameters {
+            out.push_str(&format!("  {name} = {value}\n"));
+        }
+        for constraint in strategy.constraints.iter().flatten() {
+            out.push_str(&format!("  where {constraint}\n"));
+        }
+    }
+    for variant in feature.variants.iter().flatten() {
+        out.push_str(&format!(
+            "variant {} (weight {})\n",
+            variant.name, variant.weight
+        ));
+    }
+    out
+}
+
+fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+    let mut features = load(cli.file.as_deref())?.features;
+    features.sort_by(|a, b| a.name.cmp(&b.name));
+    match cli.command {
+        Command::List => {
+            let summaries: Vec<FeatureSummary> = features
+                .iter()
+                .map(|f| FeatureSummary {
+                    name: &f.name,
+                    enabled: f.enabled,
+                    strategies: f.strategies.len(),
+                    variants: f.variants.as_ref().map_or(0, Vec::len),
+                    description: f.description.as_deref().unwrap_or_default(),
+                })
+                .collect();
+            emit(cli.format, &summaries, || {
+                let rows: Vec<Vec<String>> = summaries
+                    .iter()
+                    .map(|s| {
+                        vec![
+                            s.name.into(),
+                            s.enabled.to_string(),
+                            s.strategies.to_string(),
+                            s.variants.to_string(),
+                            s.description.lines().next().unwrap_or_default().into(),
+                        ]
+                    })
+                    .collect();
+                table(
+                    &["NAME", "ENABLED", "STRATEGIES", "VARIANTS", "DESCRIPTION"],
+                    &rows,
+                )
+            })
+        }
+        Command::Show { name } => {
+            let feature = features
+                .iter()
+                .find(|f| f.name == name)
+                .ok_or_else(|| format!("no feature named {name:?}"))?;
+            emit(cli.format, feature, || show_table(feature))
+        }
+        Command::Eval { name, context } => {
+            let context = context.into_context()?;
+            let evaluator = Evaluator::new(features, &context.app_name, "dump-features")?;
+            let evaluated = evaluator.evaluate(&name, &context);
+            let evaluation = Evaluation {
+                enabled: evaluated.enabled,
+                variant: evaluated.variant.name,
+                payload: evaluated.variant.payload,
+                reason: evaluator.explain(&name, &context).reason,
+                name,
+            };
+            emit(cli.format, &evaluation, || {
+                table(
+                    &["NAME", "ENABLED", "VARIANT", "REASON"],
+                    &[vec![
+                        evaluation.name.clone(),
+                        evaluation.enabled.to_string(),
+                        evaluation.variant.clone(),
+                        evaluation.reason.clone(),
+                    ]],
+                )
+            })
+        }
+    }
 }
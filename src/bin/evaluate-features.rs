@@ -0,0 +1,93 @@
+//! Evaluate toggles offline, from a features export and a context, and explain
+//! the results.
+//!
+//! The toggles go through `Client::memoize`, and the results printed are those
+//! of `Client::is_enabled_str` and `Client::get_variant_str`, explained by
+//! `Client::explain_str` (see `Evaluator`).
+use std::collections::HashMap;
+use std::fs;
+
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+
+use unleash_api_client::explain::Explanation;
+use unleash_api_client::Context;
+
+use common::{load, Error};
+use evaluator::Evaluator;
+
+mod common;
+mod evaluator;
+
+#[derive(Parser)]
+#[command(about = "Evaluate Unleash toggles offline and explain the results")]
+struct Cli {
+    /// A features export, as served by /api/client/features.
+    #[arg(long)]
+    features: String,
+    /// The context as JSON, or @FILE to read it from a file.
+    #[arg(long, default_value = "{}")]
+    context: String,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// The toggles to evaluate; all of them if none are given.
+    names: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Evaluation {
+    is_enabled: bool,
+    variant: String,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    payload: HashMap<String, String>,
+    explanation: Explanation,
+}
+
+fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+    let features = load(Some(&cli.features))?;
+    let context: Context = match cli.context.strip_prefix('@') {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => serde_json::from_str(&cli.context)?,
+    };
+    let mut names = cli.names;
+    if names.is_empty() {
+        names = features.features.iter().map(|f| f.name.clone()).collect();
+        names.sort();
+    }
+
+    let evaluator = Evaluator::new(features.features, &context.app_name, "evaluate-features")?;
+    let evaluations: Vec<Evaluation> = names
+        .iter()
+        .map(|name| {
+            let evaluation = evaluator.evaluate(name, &context);
+            Evaluation {
+                is_enabled: evaluation.enabled,
+                variant: evaluation.variant.name,
+                payload: evaluation.variant.payload,
+                explanation: evaluator.explain(name, &context),
+            }
+        })
+        .collect();
+    match cli.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&evaluations)?),
+        Format::Text => {
+            for evaluation in evaluations {
+                print!("{}", evaluation.explanation);
+                println!(
+                    "  is_enabled: {}, get_variant: {}",
+                    evaluation.is_enabled, evaluation.variant
+                );
+            }
+        }
+    }
+    Ok(())
+}
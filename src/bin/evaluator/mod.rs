@@ -0,0 +1,54 @@
+//! Evaluating toggles offline, shared by dump-features and evaluate-features.
+use enum_map::Enum;
+use serde::{Deserialize, Serialize};
+
+use unleash_api_client::api::Feature;
+use unleash_api_client::client::Variant;
+use unleash_api_client::explain::Explanation;
+use unleash_api_client::{Client, ClientBuilder, Context};
+
+use crate::common::Error;
+
+// The evaluation client only uses string features.
+#[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+pub enum NoFeatures {}
+
+/// A toggle evaluated as the client would.
+pub struct Evaluation {
+    /// The result of `Client::is_enabled_str`, with a default of false.
+    pub enabled: bool,
+    /// The result of `Client::get_variant_str`, with its payload.
+    pub variant: Variant,
+}
+
+/// Evaluates toggles offline. They go through `Client::memoize` exactly as
+/// when fetched from a server, so the results match production for the same
+/// context, apart from variants picked at random for contexts without
+/// identifiers.
+pub struct Evaluator {
+    client: Client<NoFeatures, reqwest::Client>,
+}
+
+impl Evaluator {
+    pub fn new(features: Vec<Feature>, app_name: &str, instance_id: &str) -> Result<Self, Error> {
+        let client = ClientBuilder::default()
+            .enable_string_features()
+            .disable_metric_submission()
+            .into_client("http://127.0.0.1/", app_name, instance_id, None)?;
+        client.memoize(features)?;
+        Ok(Self { client })
+    }
+
+    pub fn evaluate(&self, name: &str, context: &Context) -> Evaluation {
+        Evaluation {
+            enabled: self.client.is_enabled_str(name, Some(context), false),
+            variant: self.client.get_variant_str(name, context),
+        }
+    }
+
+    /// Why a toggle evaluates as it does. Variants picked at random (for
+    /// contexts without identifiers) may differ from those of `evaluate`.
+    pub fn explain(&self, name: &str, context: &Context) -> Explanation {
+        self.client.explain_str(name, Some(context))
+    }
+}
@@ -0,0 +1,39 @@
+//! Generate a feature enum from the toggles of an Unleash server or a features
+//! export file.
+//!
+//! Without `EXPORT` the toggles are fetched from the server configured in the
+//! environment (see `EnvironmentConfig`). The source is written to `--output`,
+//! or stdout.
+use std::fs;
+
+use clap::Parser;
+
+use unleash_api_client::codegen;
+
+use common::{load, Error};
+
+mod common;
+
+#[derive(Parser)]
+#[command(about = "Generate a feature enum from Unleash feature toggles")]
+struct Cli {
+    /// The name of the generated enum.
+    #[arg(long, default_value = "Features")]
+    enum_name: String,
+    /// Write the source to this file instead of stdout.
+    #[arg(long, short)]
+    output: Option<String>,
+    /// A features export to read toggles from instead of the API.
+    export: Option<String>,
+}
+
+fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+    let features = load(cli.export.as_deref())?;
+    let source = codegen::feature_enum(&features.features, &cli.enum_name);
+    match cli.output {
+        Some(path) => fs::write(path, source)?,
+        None => print!("{source}"),
+    }
+    Ok(())
+}
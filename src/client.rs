@@ -1,18 +1,18 @@
 // Copyright 2020 Cognite AS
 //! The primary interface for users of the library.
+use std::borrow::Cow;
 use std::collections::hash_map::HashMap;
 use std::default::Default;
 use std::fmt::{self, Debug, Display};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use arc_swap::ArcSwapOption;
-use chrono::Utc;
 use enum_map::{EnumArray, EnumMap};
 use futures_timer::Delay;
 use log::{debug, trace, warn};
-use rand::Rng;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use uuid::Uuid;
@@ -21,9 +21,17 @@ use crate::api::{
     self, ConstraintExpression, Feature, Features, Metrics, MetricsBucket, Registration,
     ToggleMetrics,
 };
-use crate::context::Context;
+use crate::clock::{self, SharedClock};
+use crate::context::{Context, SharedContextProvider};
+use crate::explain::{self, Explanation};
 use crate::http::{HttpClient, HTTP};
+#[cfg(feature = "opentelemetry")]
+use crate::otel::{self, ToggleFilter};
+use crate::overrides::{self, Override, Overrides};
+use crate::random::{self, RandomSource, SharedRandom};
+use crate::redact::RedactionPolicy;
 use crate::strategy;
+use crate::watch::WatchedFile;
 
 // ----------------- Variant
 
@@ -47,7 +55,8 @@ impl From<&CachedVariant> for Variant {
 }
 
 impl Variant {
-    fn disabled() -> Self {
+    /// The variant returned when a feature is disabled or has no variants.
+    pub fn disabled() -> Self {
         Self {
             name: "disabled".into(),
             ..Default::default()
@@ -55,12 +64,109 @@ impl Variant {
     }
 }
 
+// ----------------- FeatureDefaults
+
+/// Defaults declared per feature, typically implemented by the feature enum
+/// (see the `FeatureEnum` derive). `Client::is_enabled_with_defaults` and
+/// `Client::get_variant_with_defaults` fall back to them when a toggle is
+/// unknown to the server or no toggles have been fetched yet.
+pub trait FeatureDefaults {
+    /// Whether the feature is enabled by default.
+    fn default_enabled(&self) -> bool {
+        false
+    }
+
+    /// The variant selected by default.
+    fn default_variant(&self) -> Variant {
+        Variant::disabled()
+    }
+}
+
+// ----------------- Toggles
+
+/// The evaluation methods of `Client`, for code that should also accept a
+/// test double such as `testing::FakeClient` (with the `test-support`
+/// feature).
+pub trait Toggles<F> {
+    fn is_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool;
+    fn is_enabled_str(&self, feature_name: &str, context: Option<&Context>, default: bool) -> bool;
+    fn get_variant(&self, feature_enum: F, context: &Context) -> Variant;
+    fn get_variant_str(&self, feature_name: &str, context: &Context) -> Variant;
+}
+
+// ----------------- MetricsSnapshot
+
+/// The metrics counted since the start of the current bucket: what the next
+/// upload would send, so far. See `Client::metrics`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub start: chrono::DateTime<chrono::Utc>,
+    /// Counts by toggle name, including variant counts with `disabled` for
+    /// the disabled variant.
+    pub toggles: HashMap<String, ToggleMetrics>,
+}
+
+fn add_metrics(toggles: &mut HashMap<String, ToggleMetrics>, name: String, metrics: ToggleMetrics) {
+    let toggle = toggles.entry(name).or_default();
+    toggle.yes += metrics.yes;
+    toggle.no += metrics.no;
+    for (variant, count) in metrics.variants {
+        *toggle.variants.entry(variant).or_default() += count;
+    }
+}
+
+// ----------------- Health
+
+/// Outcomes of fetching toggles and uploading metrics, see `Client::health`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Health {
+    /// When toggles were last loaded, from the server or otherwise.
+    pub last_fetch: Option<chrono::DateTime<chrono::Utc>>,
+    /// Fetches (or offline reloads) that failed, in total.
+    pub fetch_errors: u64,
+    /// The number of toggles known from the last load.
+    pub features: usize,
+    /// Metrics uploads that failed, in total.
+    pub metrics_upload_failures: u64,
+}
+
+#[derive(Default)]
+struct PollStats {
+    last_fetch: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+    fetch_errors: AtomicU64,
+    metrics_upload_failures: AtomicU64,
+}
+
+// ----------------- NotLoadedPolicy
+
+/// How features evaluate before the first successful fetch of toggles.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NotLoadedPolicy {
+    /// Use the default given to `is_enabled`, as for unknown toggles.
+    #[default]
+    UseDefault,
+    /// Evaluate every feature as disabled.
+    Disabled,
+}
+
 // ----------------- ClientBuilder
 
 pub struct ClientBuilder {
+    clock: SharedClock,
+    context_provider: Option<SharedContextProvider>,
     disable_metric_submission: bool,
     enable_str_features: bool,
+    exclude_overrides_from_metrics: bool,
+    #[cfg(feature = "opentelemetry")]
+    feature_flag_events: Option<ToggleFilter>,
+    features_file: Option<PathBuf>,
     interval: u64,
+    not_loaded: NotLoadedPolicy,
+    overrides: HashMap<String, Override>,
+    overrides_file: Option<PathBuf>,
+    redaction: RedactionPolicy,
+    rng: SharedRandom,
+    static_context: Context,
     strategies: HashMap<String, strategy::Strategy>,
 }
 
@@ -73,19 +179,45 @@ impl ClientBuilder {
         authorization: Option<String>,
     ) -> Result<Client<F, C>, C::Error>
     where
-        F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+        F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
         C: HttpClient + Default,
     {
         let connection_id = Uuid::new_v4().to_string();
-        Ok(Client {
+        let pending = PendingMetrics {
+            start: self.clock.now(),
+            features: EnumMap::default(),
+            str_features: Mutex::new(HashMap::new()),
+        };
+        let mut static_context = self.static_context;
+        if static_context.app_name.is_empty() {
+            static_context.app_name = app_name.into();
+        }
+        let client = Client {
             api_url: api_url.into(),
             app_name: app_name.into(),
-            disable_metric_submission: self.disable_metric_submission,
+            clock: self.clock,
+            context_provider: self.context_provider,
+            disable_metric_submission: self.disable_metric_submission
+                || self.features_file.is_some(),
             enable_str_features: self.enable_str_features,
+            etag: Mutex::new(None),
+            exclude_overrides_from_metrics: self.exclude_overrides_from_metrics,
+            #[cfg(feature = "opentelemetry")]
+            feature_flag_events: self.feature_flag_events,
+            features_file: self
+                .features_file
+                .map(|path| Mutex::new(WatchedFile::new(path))),
+            fetched: Mutex::new(None),
             instance_id: instance_id.into(),
             connection_id: connection_id.clone(),
             interval: self.interval,
+            not_loaded: self.not_loaded,
+            overrides: Overrides::new(self.overrides, self.overrides_file),
+            pending,
+            poll_stats: PollStats::default(),
             polling: AtomicBool::new(false),
+            redaction: self.redaction,
+            rng: self.rng,
             http: HTTP::new(
                 app_name.into(),
                 instance_id.into(),
@@ -93,8 +225,27 @@ impl ClientBuilder {
                 authorization,
             )?,
             cached_state: ArcSwapOption::from(None),
+            static_context,
             strategies: Mutex::new(self.strategies),
-        })
+            taken_metrics: Mutex::default(),
+        };
+        Ok(client)
+    }
+
+    /// Set the clock used for date constraints when the context has no
+    /// `current_time`, and to time metrics buckets and registration. Defaults
+    /// to the system clock.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set a provider for the ambient context (e.g. of the current request)
+    /// used when an evaluation is given no context. An explicitly passed
+    /// context is used as is; either way the static context fills the gaps.
+    pub fn context_provider(mut self, provider: SharedContextProvider) -> Self {
+        self.context_provider = Some(provider);
+        self
     }
 
     pub fn disable_metric_submission(mut self) -> Self {
@@ -107,11 +258,101 @@ impl ClientBuilder {
         self
     }
 
+    /// Do not count evaluations decided by an override in the metrics sent to
+    /// the server.
+    pub fn exclude_overrides_from_metrics(mut self) -> Self {
+        self.exclude_overrides_from_metrics = true;
+        self
+    }
+
+    /// Add an OpenTelemetry `feature_flag` event to the active span when a
+    /// toggle `toggles` accepts (by name) is evaluated; `|_| true` selects
+    /// every toggle. See the `otel` module.
+    #[cfg(feature = "opentelemetry")]
+    pub fn feature_flag_events(
+        mut self,
+        toggles: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.feature_flag_events = Some(Arc::new(toggles));
+        self
+    }
+
+    /// Run offline: take toggles from a features export file (as served by
+    /// `/api/client/features`) instead of the server. Load it at startup with
+    /// `Client::load_features_file`, which fails if it cannot be read or
+    /// parsed; `poll_for_updates` re-reads it whenever it changes. No
+    /// registration or metrics are sent.
+    pub fn features_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.features_file = Some(path.into());
+        self
+    }
+
     pub fn interval(mut self, interval: u64) -> Self {
         self.interval = interval;
         self
     }
 
+    /// Set how features evaluate before the first fetch of toggles completes.
+    /// Those evaluations are reported in the metrics sent after the first
+    /// fetch.
+    pub fn not_loaded(mut self, policy: NotLoadedPolicy) -> Self {
+        self.not_loaded = policy;
+        self
+    }
+
+    /// Start with these overrides (see the `overrides` module), at the
+    /// precedence of those from the environment. They can be changed at
+    /// runtime through `Client::overrides`.
+    pub fn overrides(mut self, overrides: HashMap<String, Override>) -> Self {
+        self.overrides.extend(overrides);
+        self
+    }
+
+    /// Read overrides from the `UNLEASH_OVERRIDES` environment variable, e.g.
+    /// `search=on,checkout=blue`.
+    pub fn overrides_from_env(self) -> Result<Self, overrides::ParseError> {
+        let overrides = match std::env::var(overrides::ENV_VAR) {
+            Ok(list) => overrides::parse_list(&list)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(self.overrides(overrides))
+    }
+
+    /// Read overrides from a JSON file, re-read whenever it changes while
+    /// polling. The file need not exist.
+    pub fn overrides_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.overrides_file = Some(path.into());
+        self
+    }
+
+    /// Set which context fields are shown, hashed or hidden when the context
+    /// of an evaluation is logged. Defaults to showing everything.
+    pub fn redaction(mut self, policy: RedactionPolicy) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Set the random source used for random rollouts and for variant
+    /// selection when the context has no identifiers. Defaults to the thread
+    /// local generator.
+    ///
+    /// This re-registers the built in `gradualRolloutRandom` and
+    /// `flexibleRollout` strategies: register any replacements for those after
+    /// calling this.
+    pub fn rng(mut self, rng: SharedRandom) -> Self {
+        self.rng = rng.clone();
+        self.strategy("gradualRolloutRandom", strategy::random_with(rng.clone()))
+            .strategy("flexibleRollout", strategy::flexible_rollout_with(rng))
+    }
+
+    /// Set a base context merged under the context of every evaluation:
+    /// anything the per-call context leaves unset is taken from here. The
+    /// app name defaults to the client's.
+    pub fn static_context(mut self, context: Context) -> Self {
+        self.static_context = context;
+        self
+    }
+
     pub fn strategy(mut self, name: &str, strategy: strategy::Strategy) -> Self {
         self.strategies.insert(name.into(), strategy);
         self
@@ -121,9 +362,21 @@ impl ClientBuilder {
 impl Default for ClientBuilder {
     fn default() -> ClientBuilder {
         let result = ClientBuilder {
+            clock: clock::system(),
+            context_provider: None,
             disable_metric_submission: false,
             enable_str_features: false,
+            exclude_overrides_from_metrics: false,
+            #[cfg(feature = "opentelemetry")]
+            feature_flag_events: None,
+            features_file: None,
             interval: 15000,
+            not_loaded: NotLoadedPolicy::default(),
+            overrides: HashMap::new(),
+            overrides_file: None,
+            redaction: RedactionPolicy::default(),
+            rng: random::thread(),
+            static_context: Default::default(),
             strategies: Default::default(),
         };
         result
@@ -156,6 +409,9 @@ pub struct CachedFeature {
     disabled_variant_count: AtomicU64,
     // Variants for use with get_variant
     variants: Vec<CachedVariant>,
+    // The API strategies, and whether each was registered (and so compiled
+    // into `strategies`), for explanations.
+    sources: Vec<(api::Strategy, bool)>,
 }
 
 impl From<&CachedFeature> for ToggleMetrics {
@@ -169,6 +425,136 @@ impl From<&CachedFeature> for ToggleMetrics {
 }
 
 impl CachedFeature {
+    /// Evaluate the feature against a context without recording metrics.
+    pub fn raw_enabled<N: Debug>(&self, feature_name: N, context: &Context, default: bool) -> bool {
+        if self.strategies.is_empty() && self.known && !self.feature_disabled {
+            trace!("is_enabled: feature {feature_name:?} has no strategies: enabling");
+            return true;
+        }
+        for memo in self.strategies.iter() {
+            if memo(context) {
+                debug!("is_enabled: feature {feature_name:?} enabled by memo {memo:p}");
+                return true;
+            } else {
+                // Traces once per strategy (memo)
+                trace!("is_enabled: feature {feature_name:?} not enabled by memo {memo:p}");
+            }
+        }
+        if !self.known {
+            debug!("is_enabled: Unknown feature {feature_name:?}, using default {default}");
+            default
+        } else {
+            // known, non-empty, missed all strategies: disabled
+            debug!("is_enabled: feature {feature_name:?} failed all strategies, disabling");
+            false
+        }
+    }
+
+    /// Whether the feature was present in the last API response.
+    pub fn known(&self) -> bool {
+        self.known
+    }
+
+    pub(crate) fn is_feature_disabled(&self) -> bool {
+        self.feature_disabled
+    }
+
+    pub(crate) fn has_variants(&self) -> bool {
+        !self.variants.is_empty()
+    }
+
+    pub(crate) fn sources(&self) -> &[(api::Strategy, bool)] {
+        &self.sources
+    }
+
+    /// Select the variant for a context without recording metrics.
+    ///
+    /// Returns None when the feature has no variants or the hash could not be
+    /// computed.
+    pub(crate) fn select_variant<N: Debug + Display>(
+        &self,
+        feature_name: N,
+        context: &Context,
+        rng: &(dyn RandomSource + Send + Sync),
+    ) -> Option<&CachedVariant> {
+        if self.variants.is_empty() {
+            trace!("get_variant: feature {feature_name:?} no variants");
+            return None;
+        }
+        let group = format!("{feature_name}");
+        let mut remote_address: Option<String> = None;
+        let identifier = context
+            .user_id
+            .as_ref()
+            .or(context.session_id.as_ref())
+            .or_else(|| {
+                context.remote_address.as_ref().and_then({
+                    |addr| {
+                        remote_address = Some(format!("{addr:?}"));
+                        remote_address.as_ref()
+                    }
+                })
+            });
+        if identifier.is_none() {
+            trace!(
+                "get_variant: feature {feature_name:?} context has no identifiers, selecting randomly"
+            );
+            let picked = rng.random_range(0..self.variants.len() as u32);
+            return Some(&self.variants[picked as usize]);
+        }
+        let identifier = identifier.unwrap();
+        let total_weight = self.variants.iter().map(|v| v.value.weight as u32).sum();
+        strategy::normalised_variant_hash(&group, identifier, total_weight)
+            .ok()
+            .and_then(|selected_weight| {
+                let mut counter: u32 = 0;
+                for variant in self.variants.iter() {
+                    counter += variant.value.weight as u32;
+                    if counter >= selected_weight {
+                        return Some(variant);
+                    }
+                }
+                None
+            })
+    }
+
+    /// Add externally gathered metrics (e.g. from frontend SDKs) to the
+    /// counters for this feature.
+    pub fn record_metrics(&self, metrics: &ToggleMetrics) {
+        self.enabled.fetch_add(metrics.yes, Ordering::Relaxed);
+        self.disabled.fetch_add(metrics.no, Ordering::Relaxed);
+        for (name, count) in &metrics.variants {
+            if let Some(variant) = self.variants.iter().find(|v| &v.value.name == name) {
+                variant.count.fetch_add(*count, Ordering::Relaxed);
+            } else if name == "disabled" {
+                self.disabled_variant_count
+                    .fetch_add(*count, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Read and reset the counters.
+    fn take_metrics(&self) -> ToggleMetrics {
+        ToggleMetrics {
+            yes: self.enabled.swap(0, Ordering::Relaxed),
+            no: self.disabled.swap(0, Ordering::Relaxed),
+            variants: self
+                .variants
+                .iter()
+                .map(|variant| {
+                    (
+                        variant.value.name.clone(),
+                        variant.count.swap(0, Ordering::Relaxed),
+                    )
+                })
+                .chain([(
+                    "disabled".into(),
+                    self.disabled_variant_count.swap(0, Ordering::Relaxed),
+                )])
+                .collect(),
+        }
+    }
+
     fn variant_metrics(&self) -> HashMap<String, u64> {
         self.variants
             .iter()
@@ -191,6 +577,12 @@ pub struct CachedVariant {
     value: api::Variant,
 }
 
+impl CachedVariant {
+    pub(crate) fn name(&self) -> &str {
+        &self.value.name
+    }
+}
+
 impl Clone for CachedVariant {
     fn clone(&self) -> Self {
         Self {
@@ -230,6 +622,153 @@ where
     }
 }
 
+impl<F> CachedState<F>
+where
+    F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
+{
+    /// Look up a feature by its API name, whether enumerated or not.
+    pub fn feature(&self, name: &str) -> Option<&CachedFeature> {
+        match serde_plain::from_str::<F>(name) {
+            Ok(feature_enum) => Some(&self.features[feature_enum]),
+            Err(_) => self.str_features.get(name),
+        }
+    }
+
+    /// The metrics counted against this state so far, without resetting
+    /// them.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.snapshot(true)
+    }
+
+    fn snapshot(&self, str_features: bool) -> MetricsSnapshot {
+        let mut toggles: HashMap<String, ToggleMetrics> = self
+            .features
+            .iter()
+            .map(|(key, feature)| {
+                // Is this unwrap safe? Not sure.
+                (serde_plain::to_string(&key).unwrap(), feature.into())
+            })
+            .collect();
+        // Only include used str_features.
+        if str_features {
+            for (name, feature) in &self.str_features {
+                if feature.enabled.load(Ordering::Relaxed) != 0
+                    || feature.disabled.load(Ordering::Relaxed) != 0
+                {
+                    toggles.insert(name.clone(), feature.into());
+                }
+            }
+        }
+        MetricsSnapshot {
+            start: self.start,
+            toggles,
+        }
+    }
+
+    /// Iterate over every cached feature along with its API name.
+    pub fn named_features(&self) -> impl Iterator<Item = (String, &CachedFeature)> {
+        self.features
+            .iter()
+            .filter_map(|(key, feature)| {
+                serde_plain::to_string(&key)
+                    .ok()
+                    .map(|name| (name, feature))
+            })
+            .chain(
+                self.str_features
+                    .iter()
+                    .map(|(name, feature)| (name.clone(), feature)),
+            )
+    }
+}
+
+/// Evaluations made before the first fetch of toggles.
+struct PendingMetrics<F>
+where
+    F: EnumArray<CachedFeature>,
+{
+    start: chrono::DateTime<chrono::Utc>,
+    features: EnumMap<F, CachedFeature>,
+    str_features: Mutex<HashMap<String, CachedFeature>>,
+}
+
+impl<F> PendingMetrics<F>
+where
+    F: EnumArray<CachedFeature> + Clone,
+{
+    fn count(feature: &CachedFeature, enabled: bool) {
+        if enabled {
+            feature.enabled.fetch_add(1, Ordering::Relaxed);
+        } else {
+            feature.disabled.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count evaluations of the feature called `name`.
+    fn record_metrics(&self, name: &str, metrics: &ToggleMetrics)
+    where
+        F: DeserializeOwned,
+    {
+        match serde_plain::from_str::<F>(name) {
+            Ok(feature_enum) => self.features[feature_enum].record_metrics(metrics),
+            Err(_) => self
+                .str_features
+                .lock()
+                .unwrap()
+                .entry(name.into())
+                .or_default()
+                .record_metrics(metrics),
+        }
+    }
+
+    /// The pending counts of every feature that has any, reset if `take`.
+    fn counts(&self, take: bool) -> Vec<(String, ToggleMetrics)>
+    where
+        F: Serialize,
+    {
+        let read = |feature: &CachedFeature| {
+            if take {
+                feature.take_metrics()
+            } else {
+                feature.into()
+            }
+        };
+        let used = |metrics: &ToggleMetrics| {
+            metrics.yes != 0 || metrics.no != 0 || metrics.variants.values().any(|c| *c != 0)
+        };
+        let mut counts: Vec<(String, ToggleMetrics)> = self
+            .features
+            .iter()
+            .map(|(key, pending)| (serde_plain::to_string(&key).unwrap(), read(pending)))
+            .collect();
+        let mut str_features = self.str_features.lock().unwrap();
+        counts.extend(
+            str_features
+                .iter()
+                .map(|(name, pending)| (name.clone(), read(pending))),
+        );
+        if take {
+            str_features.clear();
+        }
+        counts.retain(|(_, metrics)| used(metrics));
+        counts
+    }
+
+    /// Move the pending counts into a freshly loaded state.
+    fn drain_into(&self, state: &mut CachedState<F>) {
+        for (key, pending) in &self.features {
+            state.features[key].record_metrics(&pending.take_metrics());
+        }
+        for (name, pending) in self.str_features.lock().unwrap().drain() {
+            state
+                .str_features
+                .entry(name)
+                .or_default()
+                .record_metrics(&pending.take_metrics());
+        }
+    }
+}
+
 pub struct Client<F, C>
 where
     F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
@@ -237,12 +776,36 @@ where
 {
     api_url: String,
     app_name: String,
+    clock: SharedClock,
+    context_provider: Option<SharedContextProvider>,
     disable_metric_submission: bool,
     enable_str_features: bool,
+    // of the toggles last fetched, sent as If-None-Match
+    etag: Mutex<Option<String>>,
+    exclude_overrides_from_metrics: bool,
+    // toggles to add OpenTelemetry events for
+    #[cfg(feature = "opentelemetry")]
+    feature_flag_events: Option<ToggleFilter>,
+    // offline mode: the source of toggles instead of the API
+    features_file: Option<Mutex<WatchedFile>>,
+    // the toggles last fetched, renewed when the server answers 304
+    fetched: Mutex<Option<Vec<Feature>>>,
     instance_id: String,
     connection_id: String,
     interval: u64,
+    not_loaded: NotLoadedPolicy,
+    // consulted before the cached state
+    overrides: Overrides,
+    pending: PendingMetrics<F>,
+    poll_stats: PollStats,
     polling: AtomicBool,
+    // applied to contexts in logs
+    redaction: RedactionPolicy,
+    rng: SharedRandom,
+    // merged under the context of every evaluation
+    static_context: Context,
+    // metrics of the buckets already taken, for running totals
+    taken_metrics: Mutex<HashMap<String, ToggleMetrics>>,
     // Permits making extension calls to the Unleash API not yet modelled in the Rust SDK.
     pub http: HTTP<C>,
     // known strategies: strategy_name : memoiser
@@ -270,45 +833,12 @@ where
     F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
 {
     fn is_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool {
-        fn raw_enabled<F: Debug>(
-            feature: &CachedFeature,
-            feature_enum: F,
-            context: &Context,
-            default: bool,
-        ) -> bool {
-            if feature.strategies.is_empty() && feature.known && !feature.feature_disabled {
-                trace!("is_enabled: feature {feature_enum:?} has no strategies: enabling");
-                return true;
-            }
-            for memo in feature.strategies.iter() {
-                if memo(context) {
-                    debug!(
-                        "is_enabled: feature {feature_enum:?} enabled by memo {memo:p}, context {context:?}"
-                    );
-                    return true;
-                } else {
-                    // Traces once per strategy (memo)
-                    trace!(
-                        "is_enabled: feature {feature_enum:?} not enabled by memo {memo:p}, context {context:?}"
-                    );
-                }
-            }
-            if !feature.known {
-                debug!("is_enabled: Unknown feature {feature_enum:?}, using default {default}");
-                default
-            } else {
-                // known, non-empty, missed all strategies: disabled
-                debug!("is_enabled: feature {feature_enum:?} failed all strategies, disabling");
-                false
-            }
-        }
-
-        trace!("is_enabled: feature {feature_enum:?} default {default}, context {context:?}");
+        trace!("is_enabled: feature {feature_enum:?} default {default}");
         let feature = &self.features[feature_enum.clone()];
         let default_context = &Default::default();
         let context = context.unwrap_or(default_context);
 
-        let feature_enabled = raw_enabled(feature, feature_enum, context, default);
+        let feature_enabled = feature.raw_enabled(feature_enum, context, default);
 
         if feature_enabled {
             feature.enabled.fetch_add(1, Ordering::Relaxed);
@@ -336,16 +866,12 @@ where
             }
             for memo in feature.strategies.iter() {
                 if memo(context) {
-                    debug!(
-                        "is_enabled: feature {feature_name} enabled by memo {memo:p}, context {context:?}"
-                    );
+                    debug!("is_enabled: feature {feature_name} enabled by memo {memo:p}");
                     feature.enabled.fetch_add(1, Ordering::Relaxed);
                     return true;
                 } else {
                     // Traces once per strategy (memo)
-                    trace!(
-                        "is_enabled: feature {feature_name} not enabled by memo {memo:p}, context {context:?}"
-                    );
+                    trace!("is_enabled: feature {feature_name} not enabled by memo {memo:p}");
                 }
             }
             if !feature.known {
@@ -397,6 +923,7 @@ where
                                 feature_disabled: feature.feature_disabled,
                                 strategies: feature.strategies.clone(),
                                 variants: feature.variants.clone(),
+                                sources: feature.sources.clone(),
                             }
                         }
                         for (key, feature) in &cached_state.features {
@@ -415,6 +942,7 @@ where
                             feature_disabled: false,
                             strategies: vec![],
                             variants: vec![],
+                            sources: vec![],
                         };
                         new_state
                             .str_features
@@ -447,6 +975,174 @@ where
         cache
     }
 
+    /// The metrics counted since the last upload, without resetting them,
+    /// for tests or to export to other monitoring. Toggles appear as in
+    /// uploads: every enumerated feature once toggles are loaded, string
+    /// features once evaluated.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let mut snapshot = match self.cached_state().as_ref() {
+            Some(cache) => cache.snapshot(self.enable_str_features),
+            None => MetricsSnapshot {
+                start: self.pending.start,
+                toggles: HashMap::new(),
+            },
+        };
+        for (name, pending) in self.pending.counts(false) {
+            add_metrics(&mut snapshot.toggles, name, pending);
+        }
+        snapshot
+    }
+
+    /// The metrics counted since the client was created, by toggle name: the
+    /// uploaded buckets plus the current one. Unlike `metrics`, counts only
+    /// ever increase.
+    pub fn total_metrics(&self) -> HashMap<String, ToggleMetrics> {
+        let mut totals = self.taken_metrics.lock().unwrap().clone();
+        for (name, metrics) in self.metrics().toggles {
+            add_metrics(&mut totals, name, metrics);
+        }
+        totals
+    }
+
+    /// How fetching toggles and uploading metrics has gone.
+    pub fn health(&self) -> Health {
+        Health {
+            last_fetch: *self.poll_stats.last_fetch.lock().unwrap(),
+            fetch_errors: self.poll_stats.fetch_errors.load(Ordering::Relaxed),
+            features: self.cached_state().as_ref().map_or(0, |cache| {
+                cache
+                    .named_features()
+                    .filter(|(_, feature)| feature.known())
+                    .count()
+            }),
+            metrics_upload_failures: self
+                .poll_stats
+                .metrics_upload_failures
+                .load(Ordering::Relaxed),
+        }
+    }
+
+    /// The local overrides, consulted before the cached state.
+    pub fn overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    fn find_override<N: Display>(&self, feature_name: N) -> Option<(String, Override)> {
+        if !self.overrides.active() {
+            return None;
+        }
+        let name = feature_name.to_string();
+        let value = self.overrides.get(&name)?;
+        Some((name, value))
+    }
+
+    /// Count an evaluation decided by an override, unless excluded.
+    fn count_override(&self, feature_name: &str, metrics: ToggleMetrics) {
+        if self.exclude_overrides_from_metrics {
+            return;
+        }
+        match self
+            .cached_state()
+            .as_ref()
+            .and_then(|cache| cache.feature(feature_name))
+        {
+            Some(feature) => feature.record_metrics(&metrics),
+            // Carried into the next state, like evaluations before the first
+            // fetch
+            None => self.pending.record_metrics(feature_name, &metrics),
+        }
+    }
+
+    /// The overridden result of `is_enabled`, if the feature is overridden.
+    fn override_enabled<N: Display>(&self, feature_name: N) -> Option<bool> {
+        let (name, value) = self.find_override(feature_name)?;
+        trace!("is_enabled: feature {name} overridden: {value:?}");
+        self.count_override(
+            &name,
+            ToggleMetrics {
+                yes: value.enabled.into(),
+                no: (!value.enabled).into(),
+                variants: HashMap::new(),
+            },
+        );
+        Some(value.enabled)
+    }
+
+    /// The overridden result of `get_variant`, if the feature is overridden.
+    fn override_variant<N: Display>(&self, feature_name: N, context: &Context) -> Option<Variant> {
+        let (name, value) = self.find_override(feature_name)?;
+        trace!("get_variant: feature {name} overridden: {value:?}");
+        let variant = self.overridden_variant(&name, &value, context);
+        self.count_override(
+            &name,
+            ToggleMetrics {
+                yes: value.enabled.into(),
+                no: (!value.enabled).into(),
+                variants: HashMap::from([(variant.name.clone(), 1)]),
+            },
+        );
+        Some(variant)
+    }
+
+    /// The variant of an overridden feature: the pinned one, with its payload
+    /// from the server if it has one, else selected as usual.
+    fn overridden_variant(&self, name: &str, value: &Override, context: &Context) -> Variant {
+        if !value.enabled {
+            return Variant::disabled();
+        }
+        let cache = self.cached_state();
+        let feature = cache.as_ref().and_then(|cache| cache.feature(name));
+        match (&value.variant, feature) {
+            (Some(pinned), feature) => feature
+                .and_then(|f| f.variants.iter().find(|v| v.name() == pinned))
+                .map(Variant::from)
+                .unwrap_or_else(|| Variant {
+                    name: pinned.clone(),
+                    payload: HashMap::new(),
+                    enabled: true,
+                }),
+            (None, Some(feature)) => feature
+                .select_variant(name, context, self.rng.as_ref())
+                .map(Variant::from)
+                .unwrap_or_else(Variant::disabled),
+            (None, None) => Variant::disabled(),
+        }
+    }
+
+    fn not_loaded_default(&self, default: bool) -> bool {
+        match self.not_loaded {
+            NotLoadedPolicy::UseDefault => default,
+            NotLoadedPolicy::Disabled => false,
+        }
+    }
+
+    /// The redaction applied to contexts in logs.
+    pub fn redaction(&self) -> &RedactionPolicy {
+        &self.redaction
+    }
+
+    /// The random source used for evaluation.
+    pub fn rng(&self) -> &SharedRandom {
+        &self.rng
+    }
+
+    /// The base context merged under the context of every evaluation.
+    pub fn static_context(&self) -> &Context {
+        &self.static_context
+    }
+
+    /// The context to evaluate with: the given one, else the provider's, with
+    /// gaps filled from the static context.
+    fn resolve_context<'a>(&'a self, context: Option<&'a Context>) -> Cow<'a, Context> {
+        if let Some(context) = context {
+            return context.with_defaults(&self.static_context);
+        }
+        match self.context_provider.as_ref().and_then(|p| p.context()) {
+            Some(ambient) => Cow::Owned(ambient.with_defaults(&self.static_context).into_owned()),
+            None => Cow::Borrowed(&self.static_context),
+        }
+    }
+
     /// Determine what variant (if any) of the feature the given context is
     /// selected for. This is a consistent selection within a feature only
     /// - across different features with identical variant definitions,
@@ -454,12 +1150,35 @@ where
     ///
     /// The key used to hash is the first of the username, sessionid, the host
     /// address, or a random string per call to get_variant.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "unleash.get_variant", skip_all, fields(feature = ?feature_enum), ret)
+    )]
     pub fn get_variant(&self, feature_enum: F, context: &Context) -> Variant {
-        trace!("get_variant: feature {feature_enum:?} context {context:?}");
+        let variant = self.evaluate_variant(feature_enum.clone(), context);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(ToggleName(&feature_enum), &variant.name);
+        variant
+    }
+
+    fn evaluate_variant(&self, feature_enum: F, context: &Context) -> Variant {
+        trace!(
+            "get_variant: feature {feature_enum:?} context {:?}",
+            self.redaction.redact(context)
+        );
+        let context = &*self.resolve_context(Some(context));
+        if let Some(variant) = self.override_variant(ToggleName(&feature_enum), context) {
+            return variant;
+        }
         let cache = self.cached_state();
         let cache = match cache.as_ref() {
             None => {
                 trace!("get_variant: feature {feature_enum:?} no cached state");
+                let pending = &self.pending.features[feature_enum];
+                PendingMetrics::<F>::count(pending, false);
+                pending
+                    .disabled_variant_count
+                    .fetch_add(1, Ordering::Relaxed);
                 return Variant::disabled();
             }
             Some(cache) => cache,
@@ -483,16 +1202,40 @@ where
     ///
     /// The key used to hash is the first of the username, sessionid, the host
     /// address, or a random string per call to get_variant.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "unleash.get_variant", skip_all, fields(feature = feature_name), ret)
+    )]
     pub fn get_variant_str(&self, feature_name: &str, context: &Context) -> Variant {
-        trace!("get_variant_Str: feature {feature_name} context {context:?}");
         assert!(
             self.enable_str_features,
             "String feature lookup not enabled"
         );
+        let variant = self.evaluate_variant_str(feature_name, context);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(feature_name, &variant.name);
+        variant
+    }
+
+    fn evaluate_variant_str(&self, feature_name: &str, context: &Context) -> Variant {
+        trace!(
+            "get_variant_Str: feature {feature_name} context {:?}",
+            self.redaction.redact(context)
+        );
+        let context = &*self.resolve_context(Some(context));
+        if let Some(variant) = self.override_variant(feature_name, context) {
+            return variant;
+        }
         let cache = self.cached_state();
         let cache = match cache.as_ref() {
             None => {
                 trace!("get_variant_str: feature {feature_name} no cached state");
+                let mut pending = self.pending.str_features.lock().unwrap();
+                let pending = pending.entry(feature_name.into()).or_default();
+                PendingMetrics::<F>::count(pending, false);
+                pending
+                    .disabled_variant_count
+                    .fetch_add(1, Ordering::Relaxed);
                 return Variant::disabled();
             }
             Some(cache) => cache,
@@ -525,101 +1268,319 @@ where
         }
     }
 
+    /// Record an OpenTelemetry `feature_flag` event, if selected for the
+    /// toggle.
+    #[cfg(feature = "opentelemetry")]
+    fn flag_event(&self, name: impl Display, variant: &str) {
+        if let Some(toggles) = &self.feature_flag_events {
+            let name = name.to_string();
+            if toggles(&name) {
+                otel::record(name, variant);
+            }
+        }
+    }
+
     fn _get_variant<N: Debug + Display>(
         &self,
         feature: &CachedFeature,
         feature_name: N,
         context: &Context,
     ) -> Variant {
-        if feature.variants.is_empty() {
-            trace!("get_variant: feature {feature_name:?} no variants");
-            feature
-                .disabled_variant_count
-                .fetch_add(1, Ordering::Relaxed);
-            return Variant::disabled();
-        }
-        let group = format!("{feature_name}");
-        let mut remote_address: Option<String> = None;
-        let identifier = context
-            .user_id
-            .as_ref()
-            .or(context.session_id.as_ref())
-            .or_else(|| {
-                context.remote_address.as_ref().and_then({
-                    |addr| {
-                        remote_address = Some(format!("{addr:?}"));
-                        remote_address.as_ref()
-                    }
-                })
-            });
-        if identifier.is_none() {
-            trace!(
-                "get_variant: feature {feature_name:?} context has no identifiers, selecting randomly"
-            );
-            let mut rng = rand::rng();
-            let picked = rng.random_range(0..feature.variants.len());
-            feature.variants[picked]
-                .count
-                .fetch_add(1, Ordering::Relaxed);
-            return (&feature.variants[picked]).into();
-        }
-        let identifier = identifier.unwrap();
-        let total_weight = feature.variants.iter().map(|v| v.value.weight as u32).sum();
-        strategy::normalised_variant_hash(&group, identifier, total_weight)
-            .map(|selected_weight| {
-                let mut counter: u32 = 0;
-                for variant in feature.variants.iter().as_ref() {
-                    counter += variant.value.weight as u32;
-                    if counter >= selected_weight {
-                        variant.count.fetch_add(1, Ordering::Relaxed);
-                        return variant.into();
-                    }
-                }
-
+        match feature.select_variant(feature_name, context, self.rng.as_ref()) {
+            Some(variant) => {
+                variant.count.fetch_add(1, Ordering::Relaxed);
+                variant.into()
+            }
+            None => {
                 feature
                     .disabled_variant_count
                     .fetch_add(1, Ordering::Relaxed);
                 Variant::disabled()
-            })
-            .unwrap_or_else(|_| {
-                feature
-                    .disabled_variant_count
-                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "unleash.is_enabled", skip_all, fields(feature = ?feature_enum, default), ret)
+    )]
+    pub fn is_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool {
+        let enabled = self.evaluate_enabled(feature_enum.clone(), context, default);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(ToggleName(&feature_enum), otel::enabled_variant(enabled));
+        enabled
+    }
+
+    fn evaluate_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool {
+        trace!(
+            "is_enabled: feature {feature_enum:?} default {default}, context {:?}",
+            self.redaction.redact_option(context)
+        );
+        if let Some(enabled) = self.override_enabled(ToggleName(&feature_enum)) {
+            return enabled;
+        }
+        let cache = self.cached_state();
+        let cache = match cache.as_ref() {
+            None => {
+                let enabled = self.not_loaded_default(default);
+                trace!("is_enabled: feature {feature_enum:?} no cached state, using {enabled}");
+                PendingMetrics::<F>::count(&self.pending.features[feature_enum], enabled);
+                return enabled;
+            }
+            Some(cache) => cache,
+        };
+        let context = self.resolve_context(context);
+        cache.is_enabled(feature_enum, Some(&context), default)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "unleash.is_enabled", skip_all, fields(feature = feature_name, default), ret)
+    )]
+    pub fn is_enabled_str(
+        &self,
+        feature_name: &str,
+        context: Option<&Context>,
+        default: bool,
+    ) -> bool {
+        assert!(
+            self.enable_str_features,
+            "String feature lookup not enabled"
+        );
+        let enabled = self.evaluate_enabled_str(feature_name, context, default);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(feature_name, otel::enabled_variant(enabled));
+        enabled
+    }
+
+    fn evaluate_enabled_str(
+        &self,
+        feature_name: &str,
+        context: Option<&Context>,
+        default: bool,
+    ) -> bool {
+        trace!(
+            "is_enabled: feature_str {feature_name:?} default {default}, context {:?}",
+            self.redaction.redact_option(context)
+        );
+        if let Some(enabled) = self.override_enabled(feature_name) {
+            return enabled;
+        }
+        let cache = self.cached_state();
+        let cache = match cache.as_ref() {
+            None => {
+                let enabled = self.not_loaded_default(default);
+                trace!("is_enabled: feature_str {feature_name} no cached state, using {enabled}");
+                let mut pending = self.pending.str_features.lock().unwrap();
+                PendingMetrics::<F>::count(
+                    pending.entry(feature_name.into()).or_default(),
+                    enabled,
+                );
+                return enabled;
+            }
+            Some(cache) => cache,
+        };
+        let context = self.resolve_context(context);
+        cache.is_enabled_str(feature_name, Some(&context), default, &self.cached_state)
+    }
+
+    /// `is_enabled` for the toggle called `name` in the API, falling back to
+    /// `is_enabled_str` when it is not enumerated, whether or not string
+    /// features are enabled.
+    #[cfg(any(feature = "frontend", feature = "open-feature"))]
+    pub(crate) fn is_enabled_by_name(
+        &self,
+        name: &str,
+        context: Option<&Context>,
+        default: bool,
+    ) -> bool {
+        if let Ok(feature_enum) = serde_plain::from_str::<F>(name) {
+            return self.is_enabled(feature_enum, context, default);
+        }
+        let enabled = self.evaluate_enabled_str(name, context, default);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(name, otel::enabled_variant(enabled));
+        enabled
+    }
+
+    /// `get_variant` for the toggle called `name` in the API, falling back to
+    /// `get_variant_str` when it is not enumerated, whether or not string
+    /// features are enabled.
+    #[cfg(feature = "open-feature")]
+    pub(crate) fn get_variant_by_name(&self, name: &str, context: &Context) -> Variant {
+        if let Ok(feature_enum) = serde_plain::from_str::<F>(name) {
+            return self.get_variant(feature_enum, context);
+        }
+        let variant = self.evaluate_variant_str(name, context);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(name, &variant.name);
+        variant
+    }
+
+    /// Whether the toggle called `name` is enabled and its variant, as
+    /// `is_enabled_by_name` followed by `get_variant` would give them, but
+    /// counting the evaluation once.
+    #[cfg(feature = "frontend")]
+    pub(crate) fn evaluate_by_name(&self, name: &str, context: &Context) -> (bool, Variant) {
+        if !self.is_enabled_by_name(name, Some(context), false) {
+            return (false, Variant::disabled());
+        }
+        let context = &*self.resolve_context(Some(context));
+        if let Some((name, value)) = self.find_override(name) {
+            let variant = self.overridden_variant(&name, &value, context);
+            self.count_override(
+                &name,
+                ToggleMetrics {
+                    yes: 0,
+                    no: 0,
+                    variants: HashMap::from([(variant.name.clone(), 1)]),
+                },
+            );
+            return (true, variant);
+        }
+        let cache = self.cached_state();
+        let Some(cache) = cache.as_ref() else {
+            return (true, Variant::disabled());
+        };
+        // Grouped for stickiness as by get_variant and get_variant_str
+        let variant = match serde_plain::from_str::<F>(name) {
+            Ok(feature_enum) => self._get_variant(
+                &cache.features[feature_enum.clone()],
+                EnumToString(&feature_enum),
+                context,
+            ),
+            Err(_) => match cache.str_features.get(name) {
+                Some(feature) => self._get_variant(feature, name, context),
+                None => Variant::disabled(),
+            },
+        };
+        (true, variant)
+    }
+
+    /// Like `is_enabled`, with the default taken from the feature itself. The
+    /// default is also used before any toggles have been fetched.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "unleash.is_enabled", skip_all, fields(feature = ?feature_enum), ret)
+    )]
+    pub fn is_enabled_with_defaults(&self, feature_enum: F, context: Option<&Context>) -> bool
+    where
+        F: FeatureDefaults,
+    {
+        let enabled = self.evaluate_enabled_with_defaults(feature_enum.clone(), context);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(ToggleName(&feature_enum), otel::enabled_variant(enabled));
+        enabled
+    }
+
+    fn evaluate_enabled_with_defaults(&self, feature_enum: F, context: Option<&Context>) -> bool
+    where
+        F: FeatureDefaults,
+    {
+        let default = feature_enum.default_enabled();
+        if let Some(enabled) = self.override_enabled(ToggleName(&feature_enum)) {
+            return enabled;
+        }
+        if self.cached_state().is_none() {
+            trace!("is_enabled: feature {feature_enum:?} no cached state, using default {default}");
+            PendingMetrics::<F>::count(&self.pending.features[feature_enum], default);
+            return default;
+        }
+        self.evaluate_enabled(feature_enum, context, default)
+    }
+
+    /// Like `get_variant`, but returns the feature's default variant when the
+    /// toggle is unknown or no toggles have been fetched yet. Such defaults
+    /// are not counted in metrics: the server has no variant to attribute
+    /// them to.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "unleash.get_variant", skip_all, fields(feature = ?feature_enum), ret)
+    )]
+    pub fn get_variant_with_defaults(&self, feature_enum: F, context: &Context) -> Variant
+    where
+        F: FeatureDefaults,
+    {
+        let variant = self.evaluate_variant_with_defaults(feature_enum.clone(), context);
+        #[cfg(feature = "opentelemetry")]
+        self.flag_event(ToggleName(&feature_enum), &variant.name);
+        variant
+    }
+
+    fn evaluate_variant_with_defaults(&self, feature_enum: F, context: &Context) -> Variant
+    where
+        F: FeatureDefaults,
+    {
+        let resolved = self.resolve_context(Some(context));
+        if let Some(variant) = self.override_variant(ToggleName(&feature_enum), &resolved) {
+            return variant;
+        }
+        let known = self
+            .cached_state()
+            .as_ref()
+            .map(|cache| cache.features[feature_enum.clone()].known())
+            .unwrap_or(false);
+        if known {
+            self.evaluate_variant(feature_enum, context)
+        } else {
+            trace!("get_variant: feature {feature_enum:?} unknown or not loaded, using default");
+            feature_enum.default_variant()
+        }
+    }
 
-                Variant::disabled()
-            })
+    /// Explain how a feature evaluates for a context, without recording
+    /// metrics.
+    pub fn explain(&self, feature_enum: F, context: Option<&Context>) -> Explanation {
+        let context = self.resolve_context(context);
+        let name = ToggleName(&feature_enum).to_string();
+        let explanation = match self.cached_state().as_ref() {
+            None => Explanation::not_loaded(name),
+            Some(cache) => Explanation {
+                feature: name,
+                // Variants are selected as by get_variant
+                ..explain::explain(
+                    &cache.features[feature_enum.clone()],
+                    EnumToString(&feature_enum),
+                    &context,
+                    &self.clock,
+                    self.rng.as_ref(),
+                )
+            },
+        };
+        self.explain_override(explanation, &context)
     }
 
-    pub fn is_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool {
-        trace!("is_enabled: feature {feature_enum:?} default {default}, context {context:?}");
-        let cache = self.cached_state();
-        let cache = match cache.as_ref() {
-            None => {
-                trace!("is_enabled: feature {feature_enum:?} no cached state");
-                return false;
+    /// Explain how a feature evaluates for a context, without recording
+    /// metrics.
+    pub fn explain_str(&self, feature_name: &str, context: Option<&Context>) -> Explanation {
+        let context = self.resolve_context(context);
+        let explanation = match self.cached_state().as_ref() {
+            None => Explanation::not_loaded(feature_name),
+            Some(cache) => {
+                let unknown = CachedFeature::default();
+                let feature = cache.str_features.get(feature_name).unwrap_or(&unknown);
+                explain::explain(
+                    feature,
+                    feature_name,
+                    &context,
+                    &self.clock,
+                    self.rng.as_ref(),
+                )
             }
-            Some(cache) => cache,
         };
-        cache.is_enabled(feature_enum, context, default)
+        self.explain_override(explanation, &context)
     }
 
-    pub fn is_enabled_str(
-        &self,
-        feature_name: &str,
-        context: Option<&Context>,
-        default: bool,
-    ) -> bool {
-        trace!("is_enabled: feature_str {feature_name:?} default {default}, context {context:?}");
-        assert!(
-            self.enable_str_features,
-            "String feature lookup not enabled"
-        );
-        let cache = self.cached_state();
-        let cache = match cache.as_ref() {
-            None => return false,
-            Some(cache) => cache,
-        };
-        cache.is_enabled_str(feature_name, context, default, &self.cached_state)
+    /// Apply any override to an explanation, keeping the server's strategies
+    /// for reference.
+    fn explain_override(&self, explanation: Explanation, context: &Context) -> Explanation {
+        match self.find_override(&explanation.feature) {
+            None => explanation,
+            Some((name, value)) => {
+                let variant = self.overridden_variant(&name, &value, context);
+                explanation.overridden(value, variant.name)
+            }
+        }
     }
 
     /// Memoize new features into the cached state
@@ -628,11 +1589,15 @@ where
     ///
     /// Note that this is primarily public to facilitate benchmarking;
     /// poll_for_updates is the usual way in which memoize will be called.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "unleash.memoize", skip_all, fields(features = features.len()))
+    )]
     pub fn memoize(
         &self,
         features: Vec<Feature>,
     ) -> Result<Option<Metrics>, Box<dyn std::error::Error + Send + Sync>> {
-        let now = Utc::now();
+        let now = self.clock.now();
         trace!("memoize: start with {} features", features.len());
         let source_strategies = self.strategies.lock().unwrap();
         let mut unenumerated_features: HashMap<String, CachedFeature> = HashMap::new();
@@ -651,16 +1616,21 @@ where
                         known: true,
                         feature_disabled: true,
                         variants: vec![],
+                        sources: vec![],
                     }
                 } else {
                     // TODO add variant support
                     let mut strategies = vec![];
+                    let mut sources = vec![];
                     for api_strategy in feature.strategies {
-                        if let Some(code_strategy) = source_strategies.get(&api_strategy.name) {
-                            strategies.push(strategy::constrain(
+                        let code_strategy = source_strategies.get(&api_strategy.name);
+                        sources.push((api_strategy.clone(), code_strategy.is_some()));
+                        if let Some(code_strategy) = code_strategy {
+                            strategies.push(strategy::constrain_with_clock(
                                 api_strategy.constraints,
                                 code_strategy,
                                 api_strategy.parameters,
+                                &self.clock,
                             ));
                         }
                         // Graceful degradation: ignore this unknown strategy.
@@ -682,6 +1652,7 @@ where
                         known: true,
                         feature_disabled: false,
                         variants,
+                        sources,
                     }
                 }
             };
@@ -691,38 +1662,40 @@ where
                 unenumerated_features.insert(feature.name.clone(), cached_feature);
             }
         }
-        let new_cache = CachedState {
+        let mut new_cache = CachedState {
             start: now,
             features: cached_features,
             str_features: unenumerated_features,
         };
+        if self.cached_state.load().is_none() {
+            // The first bucket covers evaluations made before this first load
+            new_cache.start = self.pending.start;
+            // Carry over evaluations made before the first load into the
+            // metrics for the new state.
+            self.pending.drain_into(&mut new_cache);
+        }
         // Now we have the new cache compiled, swap it in.
         let old = self.cached_state.swap(Some(Arc::new(new_cache)));
+        *self.poll_stats.last_fetch.lock().unwrap() = Some(now);
         trace!("memoize: swapped memoized state in");
         if let Some(old) = old {
             // send metrics here
+            let snapshot = old.snapshot(self.enable_str_features);
             let mut bucket = MetricsBucket {
-                start: old.start,
+                start: snapshot.start,
                 stop: now,
-                toggles: HashMap::new(),
+                toggles: snapshot.toggles,
             };
-            for (key, feature) in &old.features {
-                bucket.toggles.insert(
-                    // Is this unwrap safe? Not sure.
-                    serde_plain::to_string(&key).unwrap(),
-                    feature.into(),
-                );
+            // Evaluations that raced with the first load, or were decided by
+            // overrides of features missing from the state.
+            for (name, pending) in self.pending.counts(true) {
+                add_metrics(&mut bucket.toggles, name, pending);
             }
-            // Only create metrics for used str_features.
-            if self.enable_str_features {
-                for (name, feature) in &old.str_features {
-                    if feature.enabled.load(Ordering::Relaxed) != 0
-                        || feature.disabled.load(Ordering::Relaxed) != 0
-                    {
-                        bucket.toggles.insert(name.clone(), feature.into());
-                    }
-                }
+            let mut taken = self.taken_metrics.lock().unwrap();
+            for (name, metrics) in &bucket.toggles {
+                add_metrics(&mut taken, name.clone(), metrics.clone());
             }
+            drop(taken);
             let metrics = Metrics {
                 app_name: self.app_name.clone(),
                 instance_id: self.instance_id.clone(),
@@ -735,11 +1708,64 @@ where
         }
     }
 
+    /// In offline mode, load the features file if it changed since it was
+    /// last loaded, returning whether it was. A file that cannot be read or
+    /// parsed is reported and the toggles loaded before are kept.
+    pub fn reload_features_file(&self) -> bool {
+        match self.load_features_file() {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                warn!("offline: {err}");
+                self.poll_stats.fetch_errors.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// In offline mode, load the features file if it changed since it was
+    /// last loaded, returning whether it was. Meant for startup, where a file
+    /// that cannot be read or parsed should be fatal:
+    ///
+    /// ```no_run
+    /// # use enum_map::Enum;
+    /// # use serde::{Deserialize, Serialize};
+    /// # use unleash_api_client::prelude::*;
+    /// # #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    /// # enum Features {}
+    /// let client = ClientBuilder::default()
+    ///     .features_file("features.json")
+    ///     .into_client::<Features, DefaultClient>("http://127.0.0.1/", "app", "instance", None)?;
+    /// client.load_features_file()?;
+    /// # Ok::<(), Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>>(())
+    /// ```
+    pub fn load_features_file(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(file) = &self.features_file else {
+            return Ok(false);
+        };
+        let mut file = file.lock().unwrap();
+        let contents = match file
+            .read_if_changed()
+            .map_err(|err| format!("failed to read {:?}: {err}", file.path()))?
+        {
+            None => return Ok(false),
+            Some(contents) => contents,
+        };
+        let features: Features = serde_json::from_str(&contents.text)
+            .map_err(|err| format!("failed to parse {:?}: {err}", file.path()))?;
+        debug!("offline: loading features from {:?}", file.path());
+        self.memoize(features.features)?;
+        file.loaded(&contents);
+        Ok(true)
+    }
+
     /// Query the API endpoint for features and push metrics
     ///
     /// Immediately and then every self.interval milliseconds the API server is
     /// queryed for features and the previous cycles metrics are uploaded.
     ///
+    /// In offline mode (see `ClientBuilder::features_file`) the features file
+    /// is checked for changes instead, and nothing is sent.
+    ///
     /// May be dropped, or will terminate at the next polling cycle after
     /// stop_poll is called().
     pub async fn poll_for_updates(&self) {
@@ -747,79 +1773,135 @@ where
         let endpoint = Features::endpoint(&self.api_url);
         let metrics_endpoint = Metrics::endpoint(&self.api_url);
         self.polling.store(true, Ordering::Relaxed);
+        while self.features_file.is_some() {
+            self.overrides.reload();
+            self.reload_features_file();
+            Delay::new(Duration::from_millis(self.interval)).await;
+            if !self.polling.load(Ordering::Relaxed) {
+                return;
+            }
+        }
         loop {
-            debug!("poll: retrieving features");
-            match self
-                .http
-                .get_json::<Features>(&endpoint, Some(self.interval))
-                .await
-            {
-                Ok(features) => {
-                    for feature in &features.features {
-                        for strategy in &feature.strategies {
-                            if let Some(constraints) = &strategy.constraints {
-                                for constraint in constraints {
-                                    if matches!(
-                                        &constraint.expression,
-                                        ConstraintExpression::Unknown(..)
-                                    ) {
-                                        warn!("Unknown or invalid constraint expression {:?} detected in strategy '{}' in feature toggle '{}'",  
-                                            serde_json::to_string(&constraint.expression),
-                                            strategy.name,
-                                            feature.name);
-                                    }
+            self.overrides.reload();
+            self.poll_once(&endpoint, &metrics_endpoint).await;
+
+            let duration = Duration::from_millis(self.interval);
+            debug!("poll: waiting {duration:?}");
+            Delay::new(duration).await;
+
+            if !self.polling.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+    }
+
+    /// One cycle of `poll_for_updates`: fetch the features, then upload the
+    /// metrics of the previous cycle.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "unleash.poll",
+            skip_all,
+            fields(features = tracing::field::Empty, duration_ms = tracing::field::Empty)
+        )
+    )]
+    async fn poll_once(&self, endpoint: &str, metrics_endpoint: &str) {
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        debug!("poll: retrieving features");
+        let etag = self.etag.lock().unwrap().clone();
+        let features = match self
+            .http
+            .get_json_if_modified::<Features>(endpoint, Some(self.interval), etag.as_deref())
+            .await
+        {
+            Ok(None) => {
+                debug!("poll: features not modified");
+                // Renewed from the toggles last fetched, so that the poll ends
+                // the metrics bucket like any other
+                self.fetched.lock().unwrap().clone()
+            }
+            Ok(Some((features, etag))) => {
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("features", features.features.len());
+                for feature in &features.features {
+                    for strategy in &feature.strategies {
+                        if let Some(constraints) = &strategy.constraints {
+                            for constraint in constraints {
+                                if matches!(
+                                    &constraint.expression,
+                                    ConstraintExpression::Unknown(..)
+                                ) {
+                                    warn!("Unknown or invalid constraint expression {:?} detected in strategy '{}' in feature toggle '{}'",  
+                                        serde_json::to_string(&constraint.expression),
+                                        strategy.name,
+                                        feature.name);
                                 }
                             }
                         }
                     }
-
-                    match self.memoize(features.features) {
-                        Ok(None) => {}
-                        Ok(Some(metrics)) => {
-                            if !self.disable_metric_submission {
-                                let mut metrics_uploaded = false;
-                                let res = self
-                                    .http
-                                    .post_json(&metrics_endpoint, metrics, Some(self.interval))
-                                    .await;
-                                if let Ok(successful) = res {
-                                    if successful {
-                                        metrics_uploaded = true;
-                                        debug!("poll: uploaded feature metrics")
-                                    }
-                                }
-                                if !metrics_uploaded {
-                                    warn!("poll: error uploading feature metrics");
-                                }
+                }
+                *self.fetched.lock().unwrap() = Some(features.features.clone());
+                *self.etag.lock().unwrap() = etag;
+                Some(features.features)
+            }
+            Err(err) => {
+                warn!("poll: failed to retrieve features: {err:?}");
+                self.poll_stats.fetch_errors.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        };
+        if let Some(features) = features {
+            match self.memoize(features) {
+                Ok(None) => {}
+                Ok(Some(metrics)) => {
+                    if !self.disable_metric_submission {
+                        let mut metrics_uploaded = false;
+                        let res = self
+                            .http
+                            .post_json(metrics_endpoint, metrics, Some(self.interval))
+                            .await;
+                        if let Ok(successful) = res {
+                            if successful {
+                                metrics_uploaded = true;
+                                debug!("poll: uploaded feature metrics")
                             }
                         }
-                        Err(err) => {
-                            warn!("poll: failed to memoize features: {err:?}");
+                        if !metrics_uploaded {
+                            warn!("poll: error uploading feature metrics");
+                            self.poll_stats
+                                .metrics_upload_failures
+                                .fetch_add(1, Ordering::Relaxed);
                         }
                     }
                 }
                 Err(err) => {
-                    warn!("poll: failed to retrieve features: {err:?}");
+                    warn!("poll: failed to memoize features: {err:?}");
                 }
             }
-
-            let duration = Duration::from_millis(self.interval);
-            debug!("poll: waiting {duration:?}");
-            Delay::new(duration).await;
-
-            if !self.polling.load(Ordering::Relaxed) {
-                return;
-            }
         }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("duration_ms", started.elapsed().as_millis() as u64);
     }
 
     /// Register this client with the API endpoint.
+    ///
+    /// Does nothing in offline mode.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "unleash.register", skip_all, fields(app_name = %self.app_name), err)
+    )]
     pub async fn register(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        if self.features_file.is_some() {
+            debug!("register: offline, not registering");
+            return Ok(());
+        }
         let registration = Registration {
             app_name: self.app_name.clone(),
             instance_id: self.instance_id.clone(),
             connection_id: self.connection_id.clone(),
             interval: self.interval,
+            started: self.clock.now(),
             strategies: self
                 .strategies
                 .lock()
@@ -862,6 +1944,28 @@ where
     }
 }
 
+impl<F, C> Toggles<F> for Client<F, C>
+where
+    F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
+    C: HttpClient + Default,
+{
+    fn is_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool {
+        Client::is_enabled(self, feature_enum, context, default)
+    }
+
+    fn is_enabled_str(&self, feature_name: &str, context: Option<&Context>, default: bool) -> bool {
+        Client::is_enabled_str(self, feature_name, context, default)
+    }
+
+    fn get_variant(&self, feature_enum: F, context: &Context) -> Variant {
+        Client::get_variant(self, feature_enum, context)
+    }
+
+    fn get_variant_str(&self, feature_name: &str, context: &Context) -> Variant {
+        Client::get_variant_str(self, feature_name, context)
+    }
+}
+
 // DisplayForEnum
 
 /// Adapts an Enum to have Display for _get_variant so we can give consistent
@@ -888,22 +1992,44 @@ where
     }
 }
 
+/// Displays an enum feature as its toggle name in the API: the serde name,
+/// which differs from the Debug name for renamed variants.
+pub(crate) struct ToggleName<'a, T>(pub(crate) &'a T);
+
+impl<T> Display for ToggleName<'_, T>
+where
+    T: Debug + Serialize,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match serde_plain::to_string(self.0) {
+            Ok(name) => formatter.write_str(&name),
+            Err(_) => self.0.fmt(formatter),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::hash_map::HashMap;
     use std::collections::hash_set::HashSet;
     use std::default::Default;
     use std::hash::BuildHasher;
-    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
 
+    use chrono::{DateTime, TimeDelta, Utc};
     use enum_map::Enum;
     use maplit::hashmap;
     use serde::{Deserialize, Serialize};
 
-    use super::{ClientBuilder, Variant};
-    use crate::api::{self, Feature, Features, Strategy, ToggleMetrics};
+    use super::{ClientBuilder, FeatureDefaults, NotLoadedPolicy, Variant};
+    use crate::api::{
+        self, Constraint, ConstraintExpression, Feature, Features, Strategy, ToggleMetrics,
+    };
     use crate::client::{CachedFeature, CachedVariant};
-    use crate::context::{Context, IPAddress};
+    use crate::context::{Context, IPAddress, ThreadLocalContext};
+    use crate::overrides::Override;
+    use crate::random::SeededRandom;
     use crate::strategy;
 
     cfg_if::cfg_if! {
@@ -1163,6 +2289,495 @@ mod tests {
         assert!(client.is_enabled(UserFeatures::default, None, false));
     }
 
+    #[test]
+    fn date_constraints_use_clock() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            launched,
+        }
+        let launch = "2024-07-18T17:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let now = launch + TimeDelta::hours(1);
+        let client = ClientBuilder::default()
+            .clock(Arc::new(move || now))
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "launched".into(),
+                strategies: vec![Strategy {
+                    name: "default".into(),
+                    constraints: Some(vec![Constraint {
+                        context_name: "currentTime".into(),
+                        case_insensitive: false,
+                        inverted: false,
+                        expression: ConstraintExpression::DateAfter { value: launch },
+                    }]),
+                    ..Default::default()
+                }],
+            }])
+            .unwrap();
+        // No current_time: the client clock is after launch
+        assert!(client.is_enabled(UserFeatures::launched, None, false));
+        // An explicit current_time takes precedence
+        let before: Context = Context {
+            current_time: Some(launch - TimeDelta::hours(1)),
+            ..Default::default()
+        };
+        assert!(!client.is_enabled(UserFeatures::launched, Some(&before), false));
+    }
+
+    #[test]
+    fn static_context() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            eu_production,
+        }
+        let client = ClientBuilder::default()
+            .static_context(
+                Context::builder()
+                    .environment("production")
+                    .property("region", "eu")
+                    .build(),
+            )
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        assert_eq!(client.static_context().app_name, "foo");
+        let constraint = |context_name: &str, value: &str| Constraint {
+            context_name: context_name.into(),
+            case_insensitive: false,
+            inverted: false,
+            expression: ConstraintExpression::In {
+                values: vec![value.into()],
+            },
+        };
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "eu_production".into(),
+                strategies: vec![Strategy {
+                    name: "default".into(),
+                    constraints: Some(vec![
+                        constraint("appName", "foo"),
+                        constraint("environment", "production"),
+                        constraint("region", "eu"),
+                    ]),
+                    ..Default::default()
+                }],
+            }])
+            .unwrap();
+        assert!(client.is_enabled(UserFeatures::eu_production, None, false));
+        let user = Context::builder().user_id("fred").build();
+        assert!(client.is_enabled(UserFeatures::eu_production, Some(&user), false));
+        // The per-call context takes precedence
+        let us = Context::builder().property("region", "us").build();
+        assert!(!client.is_enabled(UserFeatures::eu_production, Some(&us), false));
+    }
+
+    #[test]
+    fn context_provider() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            fred_only,
+        }
+        let client = ClientBuilder::default()
+            .context_provider(Arc::new(ThreadLocalContext))
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "fred_only".into(),
+                strategies: vec![Strategy {
+                    name: "userWithId".into(),
+                    parameters: Some(hashmap!["userIds".into()=>"fred".into()]),
+                    ..Default::default()
+                }],
+            }])
+            .unwrap();
+        assert!(!client.is_enabled(UserFeatures::fred_only, None, false));
+        let fred = Context::builder().user_id("fred").build();
+        let barney = Context::builder().user_id("barney").build();
+        ThreadLocalContext::scope(fred.clone(), || {
+            assert!(client.is_enabled(UserFeatures::fred_only, None, false));
+            // An explicit context takes precedence
+            assert!(!client.is_enabled(UserFeatures::fred_only, Some(&barney), false));
+        });
+        ThreadLocalContext::scope(barney, || {
+            assert!(!client.is_enabled(UserFeatures::fred_only, None, false));
+            assert!(client.is_enabled(UserFeatures::fred_only, Some(&fred), false));
+        });
+    }
+
+    #[test]
+    fn not_loaded() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            one,
+            two,
+        }
+        let client = |policy| {
+            ClientBuilder::default()
+                .enable_string_features()
+                .not_loaded(policy)
+                .into_client::<UserFeatures, HttpClient>(
+                    "http://127.0.0.1:1234/",
+                    "foo",
+                    "test",
+                    None,
+                )
+                .unwrap()
+        };
+        let disabled = client(NotLoadedPolicy::Disabled);
+        assert!(!disabled.is_enabled(UserFeatures::one, None, true));
+        assert!(!disabled.is_enabled_str("three", None, true));
+
+        let c = client(NotLoadedPolicy::default());
+        assert!(c.is_enabled(UserFeatures::one, None, true));
+        assert!(!c.is_enabled(UserFeatures::one, None, false));
+        assert!(c.is_enabled_str("three", None, true));
+        assert_eq!(
+            c.get_variant(UserFeatures::two, &Context::default()),
+            Variant::disabled()
+        );
+        // Evaluations before the first load are reported with the first metrics
+        assert!(c.memoize(vec![]).unwrap().is_none());
+        let metrics = c.memoize(vec![]).unwrap().unwrap();
+        let toggles = &metrics.bucket.toggles;
+        assert_eq!((toggles["one"].yes, toggles["one"].no), (1, 1));
+        assert_eq!((toggles["two"].yes, toggles["two"].no), (0, 1));
+        assert_eq!(toggles["two"].variants["disabled"], 1);
+        assert_eq!((toggles["three"].yes, toggles["three"].no), (1, 0));
+        // ... and only once
+        let metrics = c.memoize(vec![]).unwrap().unwrap();
+        assert_eq!(metrics.bucket.toggles["one"].yes, 0);
+        assert!(!metrics.bucket.toggles.contains_key("three"));
+    }
+
+    #[test]
+    fn feature_defaults() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            on_by_default,
+            off_by_default,
+        }
+        impl FeatureDefaults for UserFeatures {
+            fn default_enabled(&self) -> bool {
+                matches!(self, UserFeatures::on_by_default)
+            }
+
+            fn default_variant(&self) -> Variant {
+                match self {
+                    UserFeatures::on_by_default => Variant {
+                        name: "fallback".into(),
+                        payload: HashMap::new(),
+                        enabled: true,
+                    },
+                    UserFeatures::off_by_default => Variant::disabled(),
+                }
+            }
+        }
+        let client = ClientBuilder::default()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        let context = Context::default();
+        assert!(client.is_enabled_with_defaults(UserFeatures::on_by_default, None));
+        assert!(!client.is_enabled_with_defaults(UserFeatures::off_by_default, None));
+        assert_eq!(
+            client
+                .get_variant_with_defaults(UserFeatures::on_by_default, &context)
+                .name,
+            "fallback"
+        );
+        assert!(client.memoize(vec![]).unwrap().is_none());
+        assert!(client.is_enabled_with_defaults(UserFeatures::on_by_default, None));
+        assert!(!client.is_enabled_with_defaults(UserFeatures::off_by_default, None));
+        // Still unknown once loaded
+        assert_eq!(
+            client
+                .get_variant_with_defaults(UserFeatures::on_by_default, &context)
+                .name,
+            "fallback"
+        );
+        // Only the is_enabled evaluations are counted: no variant is
+        // attributed to the defaults
+        let metrics = client.memoize(vec![]).unwrap().unwrap();
+        let toggles = &metrics.bucket.toggles;
+        let on = &toggles["on_by_default"];
+        assert_eq!((on.yes, on.no), (2, 0));
+        assert!(on.variants.values().all(|count| *count == 0));
+        let off = &toggles["off_by_default"];
+        assert!(off.variants.values().all(|count| *count == 0));
+
+        // Known toggles are counted as by get_variant
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: false,
+                created_at: None,
+                variants: None,
+                name: "off_by_default".into(),
+                strategies: vec![],
+            }])
+            .unwrap();
+        assert_eq!(
+            client.get_variant_with_defaults(UserFeatures::off_by_default, &context),
+            Variant::disabled()
+        );
+        let metrics = client.memoize(vec![]).unwrap().unwrap();
+        let off = &metrics.bucket.toggles["off_by_default"];
+        assert_eq!((off.yes, off.no), (0, 1));
+        assert_eq!(off.variants["disabled"], 1);
+    }
+
+    #[test]
+    fn metrics_snapshot() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            one,
+            two,
+        }
+        let c = ClientBuilder::default()
+            .enable_string_features()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        let context = Context::default();
+        // Before the first load only evaluated features are counted
+        c.is_enabled(UserFeatures::one, None, true);
+        let snapshot = c.metrics();
+        assert_eq!(snapshot.toggles.len(), 1);
+        assert_eq!(snapshot.toggles["one"].yes, 1);
+
+        c.memoize(variant_features().features).unwrap();
+        c.get_variant(UserFeatures::one, &context);
+        c.is_enabled_str("unknown", None, false);
+        let snapshot = c.metrics();
+        assert_eq!(
+            (snapshot.toggles["one"].yes, snapshot.toggles["one"].no),
+            (2, 0)
+        );
+        assert_eq!(snapshot.toggles["one"].variants["variantone"], 1);
+        assert_eq!(snapshot.toggles["two"].yes, 0);
+        assert_eq!(snapshot.toggles["unknown"].no, 1);
+        assert_eq!(c.cached_state().as_ref().unwrap().metrics(), snapshot);
+
+        // Snapshots do not reset the counters
+        let metrics = c.memoize(variant_features().features).unwrap().unwrap();
+        assert_eq!(metrics.bucket.start, snapshot.start);
+        assert_eq!(metrics.bucket.toggles, snapshot.toggles);
+        assert_eq!(c.metrics().toggles["one"].yes, 0);
+    }
+
+    #[test]
+    fn overrides() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            disabled,
+            one,
+        }
+        let build = |builder: ClientBuilder| {
+            builder
+                .enable_string_features()
+                .overrides(hashmap! {"disabled".into() => Override::enabled(true)})
+                .into_client::<UserFeatures, HttpClient>(
+                    "http://127.0.0.1:1234/",
+                    "foo",
+                    "test",
+                    None,
+                )
+                .unwrap()
+        };
+        let c = build(ClientBuilder::default());
+        // Overrides apply before the first fetch, too
+        assert!(c.is_enabled(UserFeatures::disabled, None, false));
+        c.memoize(variant_features().features).unwrap();
+        let context = Context::default();
+        assert!(c.is_enabled(UserFeatures::disabled, None, false));
+        assert!(c.is_enabled_str("disabled", None, false));
+
+        c.overrides().set("one", Override::enabled(false));
+        assert!(!c.is_enabled(UserFeatures::one, None, true));
+        assert_eq!(
+            c.get_variant(UserFeatures::one, &context),
+            Variant::disabled()
+        );
+        // A pinned variant keeps its payload from the server
+        c.overrides().set("one", Override::variant("variantone"));
+        let variant = c.get_variant(UserFeatures::one, &context);
+        assert_eq!(variant.name, "variantone");
+        assert_eq!(variant.payload["value"], "val1");
+        c.overrides().set("three", Override::variant("local"));
+        assert_eq!(c.get_variant_str("three", &context).name, "local");
+        assert!(c.is_enabled_str("three", None, false));
+
+        let explanation = c.explain(UserFeatures::disabled, None);
+        assert!(explanation.enabled);
+        assert_eq!(explanation.overridden, Some(Override::enabled(true)));
+        assert_eq!(c.overrides().all().len(), 3);
+        c.overrides().clear();
+        assert!(!c.is_enabled_str("three", None, false));
+        assert!(c.is_enabled(UserFeatures::disabled, None, false));
+
+        let metrics = c.memoize(variant_features().features).unwrap().unwrap();
+        let toggles = &metrics.bucket.toggles;
+        assert_eq!((toggles["disabled"].yes, toggles["disabled"].no), (4, 0));
+        assert_eq!((toggles["one"].yes, toggles["one"].no), (1, 2));
+        assert_eq!(toggles["one"].variants["variantone"], 1);
+        assert_eq!((toggles["three"].yes, toggles["three"].no), (2, 1));
+
+        let c = build(ClientBuilder::default().exclude_overrides_from_metrics());
+        c.memoize(variant_features().features).unwrap();
+        assert!(c.is_enabled(UserFeatures::disabled, None, false));
+        let metrics = c.memoize(variant_features().features).unwrap().unwrap();
+        assert_eq!(metrics.bucket.toggles["disabled"].yes, 0);
+    }
+
+    #[test]
+    fn overrides_use_toggle_names() {
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            #[serde(rename = "new.checkout")]
+            NewCheckout,
+            #[serde(rename = "one")]
+            One,
+        }
+        let c = ClientBuilder::default()
+            .overrides(hashmap! {"new.checkout".into() => Override::enabled(true)})
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        c.memoize(variant_features().features).unwrap();
+        let context = Context::default();
+        assert!(c.is_enabled(UserFeatures::NewCheckout, None, false));
+        let explanation = c.explain(UserFeatures::NewCheckout, None);
+        assert_eq!(explanation.feature, "new.checkout");
+        assert_eq!(explanation.overridden, Some(Override::enabled(true)));
+        c.overrides().set("One", Override::enabled(false));
+        assert!(c.is_enabled(UserFeatures::One, None, false));
+        c.overrides().set("one", Override::variant("variantone"));
+        assert_eq!(
+            c.get_variant(UserFeatures::One, &context).name,
+            "variantone"
+        );
+
+        let metrics = c.memoize(variant_features().features).unwrap().unwrap();
+        assert_eq!(metrics.bucket.toggles["one"].variants["variantone"], 1);
+    }
+
+    #[test]
+    fn features_file() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            disabled,
+            novariants,
+        }
+        let path = std::env::temp_dir().join(format!("features-{}.json", uuid::Uuid::new_v4()));
+        let mut features = variant_features();
+        std::fs::write(&path, serde_json::to_string(&features).unwrap()).unwrap();
+        let c = ClientBuilder::default()
+            .features_file(&path)
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        assert!(c.load_features_file().unwrap());
+        assert!(c.is_enabled(UserFeatures::novariants, None, false));
+        assert!(!c.is_enabled(UserFeatures::disabled, None, true));
+        assert!(!c.reload_features_file());
+
+        features.features[0].enabled = true;
+        std::fs::write(&path, serde_json::to_string(&features).unwrap()).unwrap();
+        // Not every filesystem has a fine grained modification time
+        c.features_file.as_ref().unwrap().lock().unwrap().forget();
+        assert!(c.reload_features_file());
+        assert!(c.is_enabled(UserFeatures::disabled, None, false));
+
+        // A broken file keeps the previous toggles
+        std::fs::write(&path, "{").unwrap();
+        c.features_file.as_ref().unwrap().lock().unwrap().forget();
+        assert!(!c.reload_features_file());
+        assert!(c.is_enabled(UserFeatures::disabled, None, false));
+        assert!(c.disable_metric_submission);
+
+        // But a broken or missing file is an error for load_features_file
+        let load = || {
+            ClientBuilder::default()
+                .features_file(&path)
+                .into_client::<UserFeatures, HttpClient>(
+                    "http://127.0.0.1:1234/",
+                    "foo",
+                    "test",
+                    None,
+                )
+                .unwrap()
+                .load_features_file()
+        };
+        assert!(load().is_err());
+        std::fs::remove_file(&path).unwrap();
+        let err = load().err().unwrap();
+        assert!(err.to_string().starts_with("failed to read"), "{err}");
+    }
+
+    #[test]
+    fn seeded_sources() {
+        let f = variant_features();
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+        enum UserFeatures {
+            disabled,
+            novariants,
+            one,
+            two,
+        }
+        let start = "2024-07-18T17:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let client = |seed| {
+            let ticks = AtomicU64::new(0);
+            ClientBuilder::default()
+                .clock(Arc::new(move || {
+                    start + TimeDelta::minutes(ticks.fetch_add(1, Ordering::Relaxed) as i64)
+                }))
+                .rng(Arc::new(SeededRandom::new(seed)))
+                .into_client::<UserFeatures, HttpClient>(
+                    "http://127.0.0.1:1234/",
+                    "foo",
+                    "test",
+                    None,
+                )
+                .unwrap()
+        };
+        let sample = |seed| -> Vec<String> {
+            let c = client(seed);
+            c.memoize(f.features.clone()).unwrap();
+            (0..32)
+                .map(|_| c.get_variant(UserFeatures::two, &Context::default()).name)
+                .collect()
+        };
+        // Without identifiers variants are picked randomly, but repeatably
+        assert_eq!(sample(3), sample(3));
+
+        // Metric buckets are timed by the clock, the first starting when the
+        // client was built
+        let c = client(3);
+        c.memoize(f.features.clone()).unwrap();
+        let metrics = c.memoize(f.features).unwrap().unwrap();
+        assert_eq!(metrics.bucket.start, start);
+        assert_eq!(metrics.bucket.stop, start + TimeDelta::minutes(2));
+    }
+
     fn variant_features() -> Features {
         Features {
             version: 1,
@@ -1565,6 +3180,7 @@ mod tests {
             feature_disabled: true,
             variants,
             disabled_variant_count: AtomicU64::new(disabled_variant_count),
+            sources: vec![],
         };
 
         let metrics: ToggleMetrics = (&feature).into();
@@ -0,0 +1,40 @@
+//! Time sources for evaluation.
+//!
+//! Date constraints compare against the context's `currentTime`, falling back
+//! to the client's clock when the caller has not set one. The clock can be
+//! replaced via `ClientBuilder::clock` to make such evaluations deterministic.
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+/// A source of the current time.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A shareable clock, as held by the client and compiled constraints.
+pub type SharedClock = Arc<dyn Clock + Send + Sync + 'static>;
+
+/// The system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl<T> Clock for T
+where
+    T: Fn() -> DateTime<Utc>,
+{
+    fn now(&self) -> DateTime<Utc> {
+        self()
+    }
+}
+
+/// The default clock: the system clock.
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
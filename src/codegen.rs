@@ -0,0 +1,215 @@
+//! Generate Rust source for a feature enum from a set of toggles.
+//!
+//! Used by the `generate-features` binary to keep a feature enum in sync with
+//! the server. The generated enum derives
+//! [`FeatureEnum`](https://docs.rs/unleash-api-client-derive), so the crate
+//! using it needs the `derive` feature. Variants are named in UpperCamelCase
+//! and renamed to the exact toggle name; features with variants also get
+//! constants for the variant names.
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::api::Feature;
+
+/// Render the source for an enum called `enum_name` with one variant per
+/// feature, sorted by toggle name.
+pub fn feature_enum(features: &[Feature], enum_name: &str) -> String {
+    let mut features: Vec<&Feature> = features.iter().collect();
+    features.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = String::new();
+    out.push_str("// Generated by generate-features: do not edit.\n");
+    out.push_str("use unleash_api_client::FeatureEnum;\n\n");
+    writeln!(out, "#[derive(FeatureEnum, PartialEq, Eq, Hash)]").unwrap();
+    writeln!(out, "pub enum {enum_name} {{").unwrap();
+    let mut idents = Idents::default();
+    for feature in &features {
+        doc_comment(&mut out, "    ", feature.description.as_deref());
+        writeln!(out, "    #[unleash(name = {:?})]", feature.name).unwrap();
+        writeln!(out, "    {},", idents.unique(camel_case(&feature.name))).unwrap();
+    }
+    out.push_str("}\n");
+
+    let with_variants: Vec<&&Feature> = features
+        .iter()
+        .filter(|f| f.variants.as_ref().is_some_and(|v| !v.is_empty()))
+        .collect();
+    if with_variants.is_empty() {
+        return out;
+    }
+    out.push_str("\n/// Variant names, by feature.\n");
+    out.push_str("pub mod variants {\n");
+    let mut modules = Idents::default();
+    for feature in with_variants {
+        writeln!(out, "    /// Variants of `{}`.", feature.name).unwrap();
+        writeln!(
+            out,
+            "    pub mod {} {{",
+            modules.unique(not_keyword(snake_case(&feature.name, "feature_")))
+        )
+        .unwrap();
+        let mut consts = Idents::default();
+        for variant in feature.variants.iter().flatten() {
+            let ident = consts.unique(snake_case(&variant.name, "variant_").to_uppercase());
+            writeln!(out, "        pub const {ident}: &str = {:?};", variant.name).unwrap();
+        }
+        out.push_str("    }\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn doc_comment(out: &mut String, indent: &str, text: Option<&str>) {
+    for line in text.unwrap_or_default().lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            writeln!(out, "{indent}///").unwrap();
+        } else {
+            writeln!(out, "{indent}/// {line}").unwrap();
+        }
+    }
+}
+
+fn words(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+fn camel_case(name: &str) -> String {
+    let mut ident: String = words(name)
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert_str(0, "Feature");
+    }
+    not_keyword(ident)
+}
+
+fn snake_case(name: &str, prefix: &str) -> String {
+    let mut ident = words(name)
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_");
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert_str(0, prefix);
+    }
+    ident
+}
+
+fn not_keyword(mut ident: String) -> String {
+    if is_keyword(&ident) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn is_keyword(ident: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "Self", "self", "as", "async", "await", "break", "const", "continue", "crate", "dyn",
+        "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+        "mod", "move", "mut", "pub", "ref", "return", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final",
+        "gen", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+    ];
+    KEYWORDS.contains(&ident)
+}
+
+/// Hands out identifiers, suffixing repeats to keep them unique.
+#[derive(Default)]
+struct Idents(HashSet<String>);
+
+impl Idents {
+    fn unique(&mut self, ident: String) -> String {
+        let mut candidate = ident.clone();
+        let mut n = 2;
+        while !self.0.insert(candidate.clone()) {
+            candidate = format!("{ident}{n}");
+            n += 1;
+        }
+        candidate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{camel_case, feature_enum, not_keyword, snake_case};
+    use crate::api::{Feature, Variant};
+
+    fn feature(name: &str, description: Option<&str>, variants: &[&str]) -> Feature {
+        Feature {
+            name: name.into(),
+            description: description.map(Into::into),
+            enabled: true,
+            strategies: vec![],
+            variants: Some(
+                variants
+                    .iter()
+                    .map(|name| Variant {
+                        name: name.to_string(),
+                        weight: 100,
+                        payload: None,
+                        overrides: None,
+                    })
+                    .collect(),
+            ),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(camel_case("checkout.new-flow"), "CheckoutNewFlow");
+        assert_eq!(camel_case("already_snake"), "AlreadySnake");
+        assert_eq!(camel_case("2fa"), "Feature2fa");
+        assert_eq!(camel_case("self"), "Self_");
+        assert_eq!(snake_case("Blue-Green", "v_"), "blue_green");
+        assert_eq!(snake_case("42", "v_"), "v_42");
+        assert_eq!(not_keyword(snake_case("type", "v_")), "type_");
+        assert_eq!(not_keyword(snake_case("self", "v_")), "self_");
+    }
+
+    #[test]
+    fn generates_enum() {
+        let features = vec![
+            feature("search", None, &[]),
+            feature(
+                "checkout.new-flow",
+                Some("The new checkout.\n\nRolled out by region."),
+                &["blue", "green-2"],
+            ),
+            feature("checkout-new-flow", None, &[]),
+        ];
+        assert_eq!(
+            feature_enum(&features, "Features"),
+            r#"// Generated by generate-features: do not edit.
+use unleash_api_client::FeatureEnum;
+
+#[derive(FeatureEnum, PartialEq, Eq, Hash)]
+pub enum Features {
+    #[unleash(name = "checkout-new-flow")]
+    CheckoutNewFlow,
+    /// The new checkout.
+    ///
+    /// Rolled out by region.
+    #[unleash(name = "checkout.new-flow")]
+    CheckoutNewFlow2,
+    #[unleash(name = "search")]
+    Search,
+}
+
+/// Variant names, by feature.
+pub mod variants {
+    /// Variants of `checkout.new-flow`.
+    pub mod checkout_new_flow {
+        pub const BLUE: &str = "blue";
+        pub const GREEN_2: &str = "green-2";
+    }
+}
+"#
+        );
+    }
+}
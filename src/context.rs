@@ -1,13 +1,18 @@
 // Copyright 2020 Cognite AS
 //! <https://docs.getunleash.io/user_guide/unleash_context>
 use chrono::Utc;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+use std::sync::Arc;
 use std::{collections::HashMap, net::IpAddr};
 
 use chrono::DateTime;
-use serde::{de, Deserialize};
+use semver::Version;
+use serde::{de, ser, Deserialize, Serialize};
 
 // Custom IP Address newtype that can be deserialised from strings e.g. 127.0.0.1 for use with tests.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct IPAddress(pub IpAddr);
 
 impl<'de> de::Deserialize<'de> for IPAddress {
@@ -22,19 +27,241 @@ impl<'de> de::Deserialize<'de> for IPAddress {
                 .map_err(de::Error::custom)
                 .map(IPAddress)
         } else {
-            unimplemented!();
+            IpAddr::deserialize(deserializer).map(IPAddress)
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize)]
+impl ser::Serialize for IPAddress {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&self.0)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl From<IpAddr> for IPAddress {
+    fn from(addr: IpAddr) -> Self {
+        IPAddress(addr)
+    }
+}
+
+/// The value of a context property.
+///
+/// Properties arriving as JSON strings are kept as strings, and constraints
+/// parse them as needed; typed values are compared without parsing. In human
+/// readable formats dates and versions serialize as strings, so they
+/// deserialize back as `String`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Date(DateTime<Utc>),
+    Semver(Version),
+}
+
+impl PropertyValue {
+    /// The value as a string, as used by string and set constraints.
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match self {
+            PropertyValue::String(s) => Cow::Borrowed(s),
+            other => Cow::Owned(other.to_string()),
+        }
+    }
+
+    /// The value as a number: numbers, or strings that parse as one.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            PropertyValue::String(s) => s.parse().ok(),
+            PropertyValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The value as a date: dates, or strings that parse as RFC3339.
+    pub fn as_date(&self) -> Option<DateTime<Utc>> {
+        match self {
+            PropertyValue::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|date| date.to_utc()),
+            PropertyValue::Date(date) => Some(*date),
+            _ => None,
+        }
+    }
+
+    /// The value as a semantic version: versions, or strings that parse as one.
+    pub fn as_semver(&self) -> Option<Cow<'_, Version>> {
+        match self {
+            PropertyValue::String(s) => s.parse().ok().map(Cow::Owned),
+            PropertyValue::Semver(version) => Some(Cow::Borrowed(version)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::String(s) => f.write_str(s),
+            PropertyValue::Number(n) => n.fmt(f),
+            PropertyValue::Bool(b) => b.fmt(f),
+            PropertyValue::Date(date) => f.write_str(&date.to_rfc3339()),
+            PropertyValue::Semver(version) => version.fmt(f),
+        }
+    }
+}
+
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.into())
+    }
+}
+
+impl From<f64> for PropertyValue {
+    fn from(value: f64) -> Self {
+        PropertyValue::Number(value)
+    }
+}
+
+impl From<i64> for PropertyValue {
+    fn from(value: i64) -> Self {
+        PropertyValue::Number(value as f64)
+    }
+}
+
+impl From<i32> for PropertyValue {
+    fn from(value: i32) -> Self {
+        PropertyValue::Number(value.into())
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(value: bool) -> Self {
+        PropertyValue::Bool(value)
+    }
+}
+
+impl From<DateTime<Utc>> for PropertyValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        PropertyValue::Date(value)
+    }
+}
+
+impl From<Version> for PropertyValue {
+    fn from(value: Version) -> Self {
+        PropertyValue::Semver(value)
+    }
+}
+
+// Non human readable formats are generally not self describing, so values are
+// tagged with their type there.
+#[derive(Deserialize, Serialize)]
+enum TaggedPropertyValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Date(DateTime<Utc>),
+    Semver(Version),
+}
+
+impl ser::Serialize for PropertyValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if serializer.is_human_readable() {
+            match self {
+                PropertyValue::String(s) => serializer.serialize_str(s),
+                PropertyValue::Number(n) => serializer.serialize_f64(*n),
+                PropertyValue::Bool(b) => serializer.serialize_bool(*b),
+                other => serializer.collect_str(other),
+            }
+        } else {
+            match self.clone() {
+                PropertyValue::String(s) => TaggedPropertyValue::String(s),
+                PropertyValue::Number(n) => TaggedPropertyValue::Number(n),
+                PropertyValue::Bool(b) => TaggedPropertyValue::Bool(b),
+                PropertyValue::Date(date) => TaggedPropertyValue::Date(date),
+                PropertyValue::Semver(version) => TaggedPropertyValue::Semver(version),
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for PropertyValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct PropertyValueVisitor;
+
+        impl de::Visitor<'_> for PropertyValueVisitor {
+            type Value = PropertyValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string, number or boolean")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(PropertyValue::Bool(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(PropertyValue::Number(v as f64))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(PropertyValue::Number(v as f64))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(PropertyValue::Number(v))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(PropertyValue::String(v.into()))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(PropertyValue::String(v))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(PropertyValueVisitor)
+        } else {
+            Ok(match TaggedPropertyValue::deserialize(deserializer)? {
+                TaggedPropertyValue::String(s) => PropertyValue::String(s),
+                TaggedPropertyValue::Number(n) => PropertyValue::Number(n),
+                TaggedPropertyValue::Bool(b) => PropertyValue::Bool(b),
+                TaggedPropertyValue::Date(date) => PropertyValue::Date(date),
+                TaggedPropertyValue::Semver(version) => PropertyValue::Semver(version),
+            })
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Context {
     pub user_id: Option<String>,
     pub session_id: Option<String>,
     pub remote_address: Option<IPAddress>,
     #[serde(default, deserialize_with = "deserialize_context_properties")]
-    pub properties: HashMap<String, String>,
+    pub properties: HashMap<String, PropertyValue>,
     #[serde(default)]
     pub app_name: String,
     #[serde(default)]
@@ -42,16 +269,387 @@ pub struct Context {
     pub current_time: Option<DateTime<Utc>>,
 }
 
+impl Context {
+    /// Build a context field by field.
+    ///
+    /// ```
+    /// use unleash_api_client::Context;
+    ///
+    /// let context = Context::builder()
+    ///     .user_id("fred")
+    ///     .remote_address("10.0.0.1".parse().unwrap())
+    ///     .property("plan", "enterprise")
+    ///     .property("seats", 25)
+    ///     .build();
+    /// assert_eq!(context.user_id.as_deref(), Some("fred"));
+    /// ```
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::default()
+    }
+
+    /// Fill in anything this context leaves unset from `defaults`: fields and
+    /// properties set here take precedence. Borrows rather than copies when
+    /// `defaults` has nothing to add.
+    pub fn with_defaults<'a>(&'a self, defaults: &'a Context) -> Cow<'a, Context> {
+        let adds = |mine: bool, theirs: bool| !mine && theirs;
+        let needed = adds(self.user_id.is_some(), defaults.user_id.is_some())
+            || adds(self.session_id.is_some(), defaults.session_id.is_some())
+            || adds(
+                self.remote_address.is_some(),
+                defaults.remote_address.is_some(),
+            )
+            || adds(!self.app_name.is_empty(), !defaults.app_name.is_empty())
+            || adds(
+                !self.environment.is_empty(),
+                !defaults.environment.is_empty(),
+            )
+            || adds(self.current_time.is_some(), defaults.current_time.is_some())
+            || defaults
+                .properties
+                .keys()
+                .any(|name| !self.properties.contains_key(name));
+        if !needed {
+            return Cow::Borrowed(self);
+        }
+        let mut merged = self.clone();
+        merged.user_id = merged.user_id.or_else(|| defaults.user_id.clone());
+        merged.session_id = merged.session_id.or_else(|| defaults.session_id.clone());
+        merged.remote_address = merged.remote_address.or(defaults.remote_address);
+        if merged.app_name.is_empty() {
+            merged.app_name.clone_from(&defaults.app_name);
+        }
+        if merged.environment.is_empty() {
+            merged.environment.clone_from(&defaults.environment);
+        }
+        merged.current_time = merged.current_time.or(defaults.current_time);
+        for (name, value) in &defaults.properties {
+            merged
+                .properties
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        Cow::Owned(merged)
+    }
+}
+
+/// Builds a [`Context`]; see [`Context::builder`].
+#[derive(Clone, Debug, Default)]
+pub struct ContextBuilder {
+    context: Context,
+}
+
+impl ContextBuilder {
+    pub fn user_id<S: Into<String>>(mut self, user_id: S) -> Self {
+        self.context.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn session_id<S: Into<String>>(mut self, session_id: S) -> Self {
+        self.context.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn remote_address(mut self, remote_address: IpAddr) -> Self {
+        self.context.remote_address = Some(IPAddress(remote_address));
+        self
+    }
+
+    pub fn app_name<S: Into<String>>(mut self, app_name: S) -> Self {
+        self.context.app_name = app_name.into();
+        self
+    }
+
+    pub fn environment<S: Into<String>>(mut self, environment: S) -> Self {
+        self.context.environment = environment.into();
+        self
+    }
+
+    pub fn current_time(mut self, current_time: DateTime<Utc>) -> Self {
+        self.context.current_time = Some(current_time);
+        self
+    }
+
+    pub fn property<S: Into<String>, V: Into<PropertyValue>>(mut self, name: S, value: V) -> Self {
+        self.context.properties.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> Context {
+        self.context
+    }
+}
+
+/// Supplies the ambient context, e.g. of the request being served, for
+/// evaluations that are not given one explicitly.
+///
+/// [`ThreadLocalContext`] suits synchronous code and `TaskLocalContext` (with
+/// the `tokio` feature) async code. Closures returning `Option<Context>` are
+/// providers too, which makes other storage easy to plug in:
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use unleash_api_client::context::SharedContextProvider;
+/// # use unleash_api_client::Context;
+/// let provider: SharedContextProvider =
+///     Arc::new(|| Some(Context::builder().user_id("fred").build()));
+/// ```
+pub trait ContextProvider {
+    /// The current context, if there is one.
+    fn context(&self) -> Option<Context>;
+}
+
+/// A shareable context provider, as held by the client.
+pub type SharedContextProvider = Arc<dyn ContextProvider + Send + Sync + 'static>;
+
+impl<T> ContextProvider for T
+where
+    T: Fn() -> Option<Context>,
+{
+    fn context(&self) -> Option<Context> {
+        self()
+    }
+}
+
+thread_local! {
+    static CURRENT_CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+/// Provides the context set for the current thread with
+/// [`ThreadLocalContext::scope`].
+///
+/// Only suitable for synchronous code: an async task can move between
+/// threads at any `.await`, and other tasks polled on the same thread would
+/// see its context. Async code should use `TaskLocalContext` (with the
+/// `tokio` feature) instead.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadLocalContext;
+
+impl ThreadLocalContext {
+    /// Run `f` with `context` as the current thread's context, restoring the
+    /// previous one afterwards (even if `f` panics).
+    pub fn scope<R, F: FnOnce() -> R>(context: Context, f: F) -> R {
+        struct Restore(Option<Context>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_CONTEXT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+        let _restore = Restore(CURRENT_CONTEXT.with(|current| current.replace(Some(context))));
+        f()
+    }
+}
+
+impl ContextProvider for ThreadLocalContext {
+    fn context(&self) -> Option<Context> {
+        CURRENT_CONTEXT.with(|current| current.borrow().clone())
+    }
+}
+
+#[cfg(feature = "tokio")]
+tokio::task_local! {
+    static TASK_CONTEXT: Context;
+}
+
+/// Provides the context set for the current task with
+/// [`TaskLocalContext::scope`], the async counterpart of
+/// [`ThreadLocalContext`].
+///
+/// The context follows the future across `.await`s and threads. Only the
+/// `task_local!` storage of tokio is used, so any executor can poll it.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaskLocalContext;
+
+#[cfg(feature = "tokio")]
+impl TaskLocalContext {
+    /// Poll `future` with `context` as the current task's context.
+    pub fn scope<F: std::future::Future>(
+        context: Context,
+        future: F,
+    ) -> tokio::task::futures::TaskLocalFuture<Context, F> {
+        TASK_CONTEXT.scope(context, future)
+    }
+
+    /// Run `f` with `context` as the current task's context, e.g. to cover
+    /// the synchronous part of a call that returns a future.
+    pub fn sync_scope<R, F: FnOnce() -> R>(context: Context, f: F) -> R {
+        TASK_CONTEXT.sync_scope(context, f)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl ContextProvider for TaskLocalContext {
+    fn context(&self) -> Option<Context> {
+        TASK_CONTEXT.try_with(Context::clone).ok()
+    }
+}
+
 fn deserialize_context_properties<'de, D>(
     deserializer: D,
-) -> Result<HashMap<String, String>, D::Error>
+) -> Result<HashMap<String, PropertyValue>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let map = HashMap::<String, Option<String>>::deserialize(deserializer)?;
-    let map: HashMap<String, String> = map
+    if !deserializer.is_human_readable() {
+        return HashMap::<String, PropertyValue>::deserialize(deserializer);
+    }
+    let map = HashMap::<String, Option<PropertyValue>>::deserialize(deserializer)?;
+    let map: HashMap<String, PropertyValue> = map
         .into_iter()
         .filter_map(|(key, value)| Some((key, value?)))
         .collect();
     Ok(map)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use maplit::hashmap;
+    use semver::Version;
+
+    #[cfg(feature = "tokio")]
+    use super::TaskLocalContext;
+    use super::{Context, ContextProvider, PropertyValue, ThreadLocalContext};
+
+    fn context() -> Context {
+        Context::builder()
+            .user_id("fred")
+            .session_id("s1")
+            .remote_address("2001:db8::1".parse().unwrap())
+            .app_name("app")
+            .environment("production")
+            .current_time("2024-07-18T17:18:25Z".parse::<DateTime<Utc>>().unwrap())
+            .property("plan", "enterprise")
+            .property("seats", 25)
+            .property("beta", true)
+            .property(
+                "signup",
+                "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+            )
+            .property("version", Version::new(1, 2, 3))
+            .build()
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = serde_json::to_value(context()).unwrap();
+        assert_eq!(json["remoteAddress"], "2001:db8::1");
+        assert_eq!(json["properties"]["seats"], 25.0);
+        assert_eq!(json["properties"]["version"], "1.2.3");
+        let parsed: Context = serde_json::from_value(json).unwrap();
+        // Dates and versions come back as strings
+        assert_eq!(
+            parsed.properties["signup"],
+            PropertyValue::String("2024-01-01T00:00:00+00:00".into())
+        );
+        assert_eq!(parsed.properties["beta"], PropertyValue::Bool(true));
+        assert_eq!(parsed.user_id, context().user_id);
+        assert_eq!(parsed.remote_address, context().remote_address);
+    }
+
+    #[test]
+    fn json_properties() {
+        let parsed: Context =
+            serde_json::from_str(r#"{"properties": {"a": "x", "b": 1.5, "c": false, "d": null}}"#)
+                .unwrap();
+        assert_eq!(
+            parsed.properties,
+            hashmap! {
+                "a".into() => "x".into(),
+                "b".into() => 1.5.into(),
+                "c".into() => false.into(),
+            }
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let encoded = bincode::serialize(&context()).unwrap();
+        let decoded: Context = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded, context());
+    }
+
+    #[test]
+    fn with_defaults() {
+        let defaults = Context::builder()
+            .app_name("app")
+            .environment("production")
+            .property("region", "eu")
+            .property("plan", "free")
+            .build();
+        let call = Context::builder()
+            .user_id("fred")
+            .property("plan", "enterprise")
+            .build();
+        let merged = call.with_defaults(&defaults);
+        assert_eq!(merged.user_id.as_deref(), Some("fred"));
+        assert_eq!(merged.app_name, "app");
+        assert_eq!(merged.environment, "production");
+        assert_eq!(merged.properties["region"], "eu".into());
+        assert_eq!(merged.properties["plan"], "enterprise".into());
+
+        // Nothing to add: no copy is made
+        let complete = merged.clone().into_owned();
+        assert!(matches!(
+            complete.with_defaults(&defaults),
+            std::borrow::Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn thread_local_provider() {
+        let provider = ThreadLocalContext;
+        assert_eq!(provider.context(), None);
+        let fred = Context::builder().user_id("fred").build();
+        let barney = Context::builder().user_id("barney").build();
+        ThreadLocalContext::scope(fred.clone(), || {
+            assert_eq!(provider.context().as_ref(), Some(&fred));
+            ThreadLocalContext::scope(barney.clone(), || {
+                assert_eq!(provider.context().as_ref(), Some(&barney));
+            });
+            assert_eq!(provider.context().as_ref(), Some(&fred));
+            // Other threads are unaffected
+            std::thread::spawn(move || assert_eq!(provider.context(), None))
+                .join()
+                .unwrap();
+        });
+        assert_eq!(provider.context(), None);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn task_local_provider() {
+        let provider = TaskLocalContext;
+        assert_eq!(provider.context(), None);
+        let fred = Context::builder().user_id("fred").build();
+        let barney = Context::builder().user_id("barney").build();
+        let task = TaskLocalContext::scope(fred.clone(), async move {
+            futures::future::ready(()).await;
+            assert_eq!(provider.context().as_ref(), Some(&fred));
+            TaskLocalContext::sync_scope(barney.clone(), || {
+                assert_eq!(provider.context().as_ref(), Some(&barney));
+            });
+            assert_eq!(provider.context().as_ref(), Some(&fred));
+        });
+        // Not set while the task isn't being polled
+        assert_eq!(provider.context(), None);
+        futures::executor::block_on(task);
+        assert_eq!(provider.context(), None);
+    }
+
+    #[test]
+    fn typed_accessors() {
+        assert_eq!(PropertyValue::from("2.5").as_number(), Some(2.5));
+        assert_eq!(PropertyValue::from(3).as_number(), Some(3.0));
+        assert_eq!(PropertyValue::from(true).as_number(), None);
+        assert_eq!(
+            PropertyValue::from("1.2.3").as_semver().unwrap().as_ref(),
+            &Version::new(1, 2, 3)
+        );
+        assert_eq!(
+            PropertyValue::from("2024-01-01T02:00:00+02:00").as_date(),
+            Some("2024-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(PropertyValue::from(false).as_str(), "false");
+    }
+}
@@ -0,0 +1,300 @@
+//! Explanations of how a feature evaluates for a context.
+//!
+//! See [`Client::explain`](crate::Client::explain). Explaining re-runs the
+//! same compiled strategies as `is_enabled`, and additionally evaluates each
+//! constraint on its own so the failing ones can be pointed out. No metrics
+//! are recorded.
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+
+use serde::Serialize;
+
+use crate::client::CachedFeature;
+use crate::clock::SharedClock;
+use crate::context::Context;
+use crate::overrides::Override;
+use crate::random::RandomSource;
+use crate::strategy;
+
+/// Why a feature evaluated as it did.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Explanation {
+    pub feature: String,
+    /// The result `is_enabled` gives with a default of false.
+    pub enabled: bool,
+    pub reason: String,
+    pub strategies: Vec<StrategyExplanation>,
+    /// The selected variant, `disabled` if none.
+    pub variant: String,
+    /// What the variant selection was based on, when there are variants.
+    pub variant_reason: Option<String>,
+    /// The local override deciding the result, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden: Option<Override>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyExplanation {
+    pub name: String,
+    pub parameters: BTreeMap<String, String>,
+    /// Unregistered strategies are ignored by the client.
+    pub registered: bool,
+    pub constraints: Vec<ConstraintExplanation>,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstraintExplanation {
+    pub constraint: String,
+    pub passed: bool,
+}
+
+impl Explanation {
+    pub(crate) fn not_loaded<N: Display>(feature_name: N) -> Self {
+        Self {
+            feature: feature_name.to_string(),
+            enabled: false,
+            reason: "no toggles have been fetched yet".into(),
+            strategies: vec![],
+            variant: "disabled".into(),
+            variant_reason: None,
+            overridden: None,
+        }
+    }
+
+    pub(crate) fn overridden(self, value: Override, variant: String) -> Self {
+        let pinned = value.enabled && value.variant.is_some();
+        Self {
+            enabled: value.enabled,
+            reason: format!(
+                "overridden locally, {}",
+                if value.enabled { "on" } else { "off" }
+            ),
+            variant,
+            variant_reason: if pinned {
+                Some("pinned by the override".into())
+            } else {
+                self.variant_reason.filter(|_| value.enabled)
+            },
+            overridden: Some(value),
+            ..self
+        }
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.enabled { "enabled" } else { "disabled" };
+        writeln!(f, "{}: {state} ({})", self.feature, self.reason)?;
+        for strategy in &self.strategies {
+            let result = match (strategy.registered, strategy.enabled) {
+                (false, _) => "not registered",
+                (true, true) => "matched",
+                (true, false) => "not matched",
+            };
+            write!(f, "  strategy {}", strategy.name)?;
+            for (name, value) in &strategy.parameters {
+                write!(f, " {name}={value}")?;
+            }
+            writeln!(f, ": {result}")?;
+            for constraint in &strategy.constraints {
+                let mark = if constraint.passed { "pass" } else { "FAIL" };
+                writeln!(f, "    [{mark}] {}", constraint.constraint)?;
+            }
+        }
+        write!(f, "  variant: {}", self.variant)?;
+        if let Some(reason) = &self.variant_reason {
+            write!(f, " ({reason})")?;
+        }
+        writeln!(f)
+    }
+}
+
+pub(crate) fn explain<N: Debug + Display>(
+    feature: &CachedFeature,
+    feature_name: N,
+    context: &Context,
+    clock: &SharedClock,
+    rng: &(dyn RandomSource + Send + Sync),
+) -> Explanation {
+    let mut memos = feature.strategies.iter();
+    let strategies: Vec<StrategyExplanation> = feature
+        .sources()
+        .iter()
+        .map(|(source, registered)| {
+            let enabled = *registered && memos.next().is_some_and(|memo| memo(context));
+            let constraints = source
+                .constraints
+                .iter()
+                .flatten()
+                .map(|constraint| ConstraintExplanation {
+                    constraint: constraint.to_string(),
+                    passed: strategy::constrain_with_clock(
+                        Some(vec![constraint.clone()]),
+                        &strategy::default,
+                        None,
+                        clock,
+                    )(context),
+                })
+                .collect();
+            StrategyExplanation {
+                name: source.name.clone(),
+                parameters: source
+                    .parameters
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+                registered: *registered,
+                constraints,
+                enabled,
+            }
+        })
+        .collect();
+
+    let enabled = feature.raw_enabled(&feature_name, context, false);
+    let reason = if !feature.known() {
+        "unknown toggle: the caller's default applies".to_string()
+    } else if feature.is_feature_disabled() {
+        "toggle is disabled".to_string()
+    } else if feature.strategies.is_empty() {
+        "toggle has no strategies".to_string()
+    } else if let Some(strategy) = strategies.iter().find(|s| s.enabled) {
+        format!("enabled by strategy {}", strategy.name)
+    } else {
+        "no strategy matched".to_string()
+    };
+
+    let (variant, variant_reason) = if !enabled || !feature.has_variants() {
+        ("disabled".to_string(), None)
+    } else {
+        let basis = match (
+            &context.user_id,
+            &context.session_id,
+            &context.remote_address,
+        ) {
+            (Some(user_id), _, _) => format!("hashed on userId {user_id}"),
+            (None, Some(session_id), _) => format!("hashed on sessionId {session_id}"),
+            (None, None, Some(addr)) => format!("hashed on remoteAddress {:?}", addr.0),
+            (None, None, None) => "picked at random: no identifiers in the context".into(),
+        };
+        let variant = feature
+            .select_variant(&feature_name, context, rng)
+            .map(|v| v.name().to_string())
+            .unwrap_or_else(|| "disabled".into());
+        (variant, Some(basis))
+    };
+
+    Explanation {
+        feature: feature_name.to_string(),
+        enabled,
+        reason,
+        strategies,
+        variant,
+        variant_reason,
+        overridden: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enum_map::Enum;
+    use maplit::hashmap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::{Constraint, ConstraintExpression, Feature, Strategy, Variant};
+    use crate::client::ClientBuilder;
+    use crate::context::Context;
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "reqwest")] {
+            use reqwest::Client as HttpClient;
+        } else if #[cfg(feature = "reqwest-11")] {
+            use reqwest_11::Client as HttpClient;
+        }
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    enum UserFeatures {
+        checkout,
+    }
+
+    #[test]
+    fn explains_strategies_and_variants() {
+        let client = ClientBuilder::default()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        let region = |values: &[&str]| Constraint {
+            context_name: "region".into(),
+            case_insensitive: false,
+            inverted: false,
+            expression: ConstraintExpression::In {
+                values: values.iter().map(|v| v.to_string()).collect(),
+            },
+        };
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                name: "checkout".into(),
+                variants: Some(vec![Variant {
+                    name: "blue".into(),
+                    weight: 100,
+                    payload: None,
+                    overrides: None,
+                }]),
+                strategies: vec![
+                    Strategy {
+                        name: "custom".into(),
+                        ..Default::default()
+                    },
+                    Strategy {
+                        name: "default".into(),
+                        constraints: Some(vec![region(&["eu"]), region(&["eu", "us"])]),
+                        ..Default::default()
+                    },
+                ],
+            }])
+            .unwrap();
+
+        let us = Context {
+            user_id: Some("fred".into()),
+            properties: hashmap! {"region".into() => "us".into()},
+            ..Default::default()
+        };
+        let explanation = client.explain(UserFeatures::checkout, Some(&us));
+        assert!(!explanation.enabled);
+        assert_eq!(explanation.reason, "no strategy matched");
+        assert!(!explanation.strategies[0].registered);
+        let constraints = &explanation.strategies[1].constraints;
+        assert_eq!(constraints[0].constraint, "region IN [eu]");
+        assert!(!constraints[0].passed);
+        assert!(constraints[1].passed);
+        assert_eq!(explanation.variant, "disabled");
+
+        let eu = Context {
+            properties: hashmap! {"region".into() => "eu".into()},
+            ..us
+        };
+        let explanation = client.explain(UserFeatures::checkout, Some(&eu));
+        assert!(explanation.enabled);
+        assert_eq!(explanation.reason, "enabled by strategy default");
+        assert_eq!(explanation.variant, "blue");
+        assert_eq!(
+            explanation.variant_reason.as_deref(),
+            Some("hashed on userId fred")
+        );
+        assert!(!explanation.to_string().contains("[FAIL]"));
+        assert!(explanation.to_string().contains("[pass] region IN [eu]"));
+
+        // Explaining records no metrics
+        let metrics = client.memoize(vec![]).unwrap().unwrap();
+        assert_eq!(metrics.bucket.toggles["checkout"].yes, 0);
+        assert_eq!(metrics.bucket.toggles["checkout"].no, 0);
+    }
+}
@@ -0,0 +1,540 @@
+//! An embeddable server for the Unleash Frontend API.
+//!
+//! <https://docs.getunleash.io/reference/front-end-api>
+//!
+//! Browser and mobile SDKs talk to the frontend API rather than the client API:
+//! the server evaluates every toggle for the supplied context and returns only
+//! the enabled ones, and the SDKs post their usage back as metrics. This module
+//! answers those requests from the cached state of a [`Client`], so a Rust
+//! service can front its browser apps without an Unleash Edge or proxy
+//! deployment.
+//!
+//! [`FrontendApi::handle`] works in terms of the `http` crate request and
+//! response types, so it can be mounted in any server framework built on
+//! them.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use enum_map::EnumArray;
+use http::{header, Method, Request, Response, StatusCode};
+use log::{debug, trace, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{
+    FrontendFeatures, FrontendMetrics, FrontendPayload, FrontendToggle, FrontendVariant,
+};
+use crate::client::{CachedFeature, Client};
+use crate::context::{Context, IPAddress};
+use crate::http::HttpClient;
+use crate::redact::RedactionPolicy;
+
+/// Decide whether a frontend token (the raw `Authorization` header value) is
+/// permitted to use the API.
+pub type TokenValidator = Box<dyn Fn(&str) -> bool + Sync + Send + 'static>;
+
+/// Serves the frontend API from a client's cached state.
+pub struct FrontendApi<F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    client: Arc<Client<F, C>>,
+    base_path: String,
+    token_validator: Option<TokenValidator>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EvaluateBody {
+    #[serde(default)]
+    context: Option<Context>,
+}
+
+impl<F, C> FrontendApi<F, C>
+where
+    F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
+    C: HttpClient + Default,
+{
+    /// Serve requests under `/api/frontend` from `client`.
+    ///
+    /// Without a token validator every request is accepted.
+    pub fn new(client: Arc<Client<F, C>>) -> Self {
+        Self {
+            client,
+            base_path: "/api/frontend".into(),
+            token_validator: None,
+        }
+    }
+
+    /// Serve requests under a different path prefix.
+    pub fn base_path(mut self, base_path: &str) -> Self {
+        self.base_path = base_path.trim_end_matches('/').into();
+        self
+    }
+
+    /// Require requests to carry an `Authorization` header accepted by
+    /// `validator`.
+    pub fn token_validator(mut self, validator: TokenValidator) -> Self {
+        self.token_validator = Some(validator);
+        self
+    }
+
+    /// Handle a single request.
+    ///
+    /// Supported routes, relative to the base path:
+    /// - `GET /`: evaluate all toggles for a context given as query parameters.
+    /// - `POST /`: evaluate all toggles for a context given as a JSON body.
+    /// - `POST /client/metrics`: record frontend SDK metrics against the client.
+    pub fn handle<B: AsRef<[u8]>>(&self, request: &Request<B>) -> Response<Vec<u8>> {
+        let path = request.uri().path().trim_end_matches('/');
+        let route = match path.strip_prefix(self.base_path.as_str()) {
+            Some(route) => route,
+            None => return error_response(StatusCode::NOT_FOUND, "Not found"),
+        };
+        if !self.authorized(request) {
+            return error_response(StatusCode::UNAUTHORIZED, "Unauthorized");
+        }
+        match (request.method(), route) {
+            (&Method::GET, "") => {
+                let query = request.uri().query().unwrap_or_default();
+                let context = context_from_query(query, self.client.redaction());
+                self.evaluate_all(&context)
+            }
+            (&Method::POST, "") => {
+                let body = request.body().as_ref();
+                let body: EvaluateBody = if body.is_empty() {
+                    EvaluateBody::default()
+                } else {
+                    match serde_json::from_slice(body) {
+                        Ok(body) => body,
+                        Err(err) => {
+                            debug!("frontend: invalid context body: {err}");
+                            return error_response(StatusCode::BAD_REQUEST, "Invalid context");
+                        }
+                    }
+                };
+                self.evaluate_all(&body.context.unwrap_or_default())
+            }
+            (&Method::POST, "/client/metrics") => {
+                match serde_json::from_slice::<FrontendMetrics>(request.body().as_ref()) {
+                    Ok(metrics) => {
+                        self.record_metrics(&metrics);
+                        json_response(StatusCode::ACCEPTED, &serde_json::json!({}))
+                    }
+                    Err(err) => {
+                        debug!("frontend: invalid metrics body: {err}");
+                        error_response(StatusCode::BAD_REQUEST, "Invalid metrics")
+                    }
+                }
+            }
+            (_, "") | (_, "/client/metrics") => {
+                error_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+            }
+            _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    fn authorized<B>(&self, request: &Request<B>) -> bool {
+        let validator = match &self.token_validator {
+            None => return true,
+            Some(validator) => validator,
+        };
+        request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|token| token.to_str().ok())
+            .map(validator)
+            .unwrap_or(false)
+    }
+
+    /// Evaluate every known or overridden toggle for a context, as
+    /// `Client::is_enabled` and `Client::get_variant` do: overrides apply and
+    /// the evaluations are counted in the client's metrics.
+    pub fn evaluate(&self, context: &Context) -> FrontendFeatures {
+        trace!(
+            "frontend: evaluating all toggles, context {:?}",
+            self.client.redaction().redact(context)
+        );
+        let mut names: BTreeSet<String> = self.client.overrides().all().into_keys().collect();
+        match self.client.cached_state().as_ref() {
+            None => trace!("frontend: no cached state"),
+            Some(cache) => names.extend(
+                cache
+                    .named_features()
+                    .filter(|(_, feature)| feature.known())
+                    .map(|(name, _)| name),
+            ),
+        }
+        let toggles = names
+            .into_iter()
+            .filter_map(|name| {
+                let (enabled, variant) = self.client.evaluate_by_name(&name, context);
+                if !enabled {
+                    return None;
+                }
+                let payload = match (variant.payload.get("type"), variant.payload.get("value")) {
+                    (Some(_type), Some(value)) => Some(FrontendPayload {
+                        _type: _type.clone(),
+                        value: value.clone(),
+                    }),
+                    _ => None,
+                };
+                Some(FrontendToggle {
+                    name,
+                    enabled: true,
+                    variant: FrontendVariant {
+                        name: variant.name,
+                        enabled: variant.enabled,
+                        feature_enabled: true,
+                        payload,
+                    },
+                    impression_data: false,
+                })
+            })
+            .collect();
+        FrontendFeatures { toggles }
+    }
+
+    fn evaluate_all(&self, context: &Context) -> Response<Vec<u8>> {
+        json_response(StatusCode::OK, &self.evaluate(context))
+    }
+
+    /// Add metrics reported by a frontend SDK to the client's counters, so
+    /// they are included in the next upload to the Unleash API.
+    pub fn record_metrics(&self, metrics: &FrontendMetrics) {
+        let cache = self.client.cached_state();
+        let cache = match cache.as_ref() {
+            None => {
+                debug!("frontend: no cached state, dropping metrics");
+                return;
+            }
+            Some(cache) => cache,
+        };
+        for (name, toggle_metrics) in &metrics.bucket.toggles {
+            match cache.feature(name) {
+                Some(feature) => feature.record_metrics(toggle_metrics),
+                None => trace!("frontend: metrics for unknown feature {name}, ignoring"),
+            }
+        }
+    }
+}
+
+/// Build a context from frontend API query parameters.
+///
+/// Well known context fields are taken from their camelCase names; any other
+/// parameter, or one of the form `properties[name]`, becomes a property. Values
+/// that cannot be parsed are logged as `redaction` allows.
+fn context_from_query(query: &str, redaction: &RedactionPolicy) -> Context {
+    let mut context = Context::default();
+    let mut properties = HashMap::new();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "userId" => context.user_id = Some(value.into_owned()),
+            "sessionId" => context.session_id = Some(value.into_owned()),
+            "remoteAddress" => match value.parse() {
+                Ok(addr) => context.remote_address = Some(IPAddress(addr)),
+                Err(_) => warn!(
+                    "frontend: could not parse remoteAddress {:?}",
+                    redaction.value("remoteAddress", &value)
+                ),
+            },
+            "appName" => context.app_name = value.into_owned(),
+            "environment" => context.environment = value.into_owned(),
+            "currentTime" => match value.parse() {
+                Ok(time) => context.current_time = Some(time),
+                Err(_) => warn!(
+                    "frontend: could not parse currentTime {:?}",
+                    redaction.value("currentTime", &value)
+                ),
+            },
+            key => {
+                let name = key
+                    .strip_prefix("properties[")
+                    .and_then(|key| key.strip_suffix(']'))
+                    .unwrap_or(key);
+                properties.insert(name.to_string(), value.into_owned().into());
+            }
+        }
+    }
+    context.properties = properties;
+    context
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Vec<u8>> {
+    match serde_json::to_vec(body) {
+        Ok(body) => Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .unwrap(),
+        Err(err) => {
+            warn!("frontend: failed to serialize response: {err}");
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(vec![])
+                .unwrap()
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    json_response(status, &serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use enum_map::Enum;
+    use http::{Request, StatusCode};
+    use maplit::hashmap;
+    use serde::{Deserialize, Serialize};
+
+    use super::FrontendApi;
+    use crate::api::{self, Feature, FrontendFeatures, Strategy};
+    use crate::client::ClientBuilder;
+    use crate::context::Context;
+    use crate::overrides::Override;
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "reqwest")] {
+            use reqwest::Client as HttpClient;
+        } else if #[cfg(feature = "reqwest-11")] {
+            use reqwest_11::Client as HttpClient;
+        } else {
+            compile_error!("Cannot run test suite without a client enabled");
+        }
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    enum UserFeatures {
+        default,
+        userWithId,
+        disabled,
+        variants,
+    }
+
+    fn features() -> Vec<Feature> {
+        vec![
+            Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "default".into(),
+                strategies: vec![Strategy {
+                    name: "default".into(),
+                    ..Default::default()
+                }],
+            },
+            Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "userWithId".into(),
+                strategies: vec![Strategy {
+                    name: "userWithId".into(),
+                    parameters: Some(hashmap!["userIds".into()=>"present".into()]),
+                    ..Default::default()
+                }],
+            },
+            Feature {
+                description: None,
+                enabled: false,
+                created_at: None,
+                variants: None,
+                name: "disabled".into(),
+                strategies: vec![],
+            },
+            Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: Some(vec![api::Variant {
+                    name: "blue".into(),
+                    weight: 100,
+                    payload: Some(hashmap![
+                        "type".into() => "string".into(),
+                        "value".into() => "val1".into()]),
+                    overrides: None,
+                }]),
+                name: "variants".into(),
+                strategies: vec![],
+            },
+            Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "unenumerated".into(),
+                strategies: vec![],
+            },
+        ]
+    }
+
+    fn api() -> FrontendApi<UserFeatures, HttpClient> {
+        let client = ClientBuilder::default()
+            .enable_string_features()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        client.memoize(features()).unwrap();
+        FrontendApi::new(Arc::new(client))
+    }
+
+    fn names(response: &http::Response<Vec<u8>>) -> Vec<String> {
+        let features: FrontendFeatures = serde_json::from_slice(response.body()).unwrap();
+        features.toggles.into_iter().map(|t| t.name).collect()
+    }
+
+    #[test]
+    fn evaluate_all_get() {
+        let api = api();
+        let request = Request::get("/api/frontend").body(vec![]).unwrap();
+        let response = api.handle(&request);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            names(&response),
+            vec!["default", "unenumerated", "variants"]
+        );
+
+        let request = Request::get("/api/frontend?userId=present&properties[x]=y")
+            .body(vec![])
+            .unwrap();
+        let response = api.handle(&request);
+        assert_eq!(
+            names(&response),
+            vec!["default", "unenumerated", "userWithId", "variants"]
+        );
+    }
+
+    #[test]
+    fn evaluate_all_post() {
+        let api = api();
+        let request = Request::post("/api/frontend/")
+            .body(br#"{"context": {"userId": "present"}}"#.to_vec())
+            .unwrap();
+        let response = api.handle(&request);
+        assert_eq!(response.status(), StatusCode::OK);
+        let features: FrontendFeatures = serde_json::from_slice(response.body()).unwrap();
+        let variants = features
+            .toggles
+            .iter()
+            .find(|t| t.name == "variants")
+            .unwrap();
+        assert_eq!(variants.variant.name, "blue");
+        assert!(variants.variant.enabled);
+        assert_eq!(variants.variant.payload.as_ref().unwrap().value, "val1");
+        let default = features
+            .toggles
+            .iter()
+            .find(|t| t.name == "default")
+            .unwrap();
+        assert_eq!(default.variant.name, "disabled");
+        assert!(!default.variant.enabled);
+        assert!(default.variant.feature_enabled);
+
+        let request = Request::post("/api/frontend")
+            .body(b"not json".to_vec())
+            .unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn evaluations_apply_overrides_and_are_counted() {
+        let api = api();
+        let overrides = api.client.overrides();
+        overrides.set("disabled", Override::enabled(true));
+        overrides.set("variants", Override::enabled(false));
+        overrides.set("onlyOverridden", Override::variant("red"));
+        let evaluated = api.evaluate(&Context::default());
+        let names: Vec<_> = evaluated.toggles.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["default", "disabled", "onlyOverridden", "unenumerated"]
+        );
+        let only_overridden = &evaluated.toggles[2];
+        assert_eq!(only_overridden.variant.name, "red");
+        assert!(only_overridden.variant.enabled);
+
+        let metrics = api
+            .client
+            .memoize(features())
+            .unwrap()
+            .expect("metrics from the previous cache");
+        let default = metrics.bucket.toggles.get("default").unwrap();
+        assert_eq!((default.yes, default.no), (1, 0));
+        assert_eq!(*default.variants.get("disabled").unwrap(), 1);
+        assert_eq!(metrics.bucket.toggles.get("userWithId").unwrap().no, 1);
+        assert_eq!(metrics.bucket.toggles.get("variants").unwrap().no, 1);
+    }
+
+    #[test]
+    fn metrics_are_recorded() {
+        let api = api();
+        let body = br#"{
+            "appName": "web",
+            "instanceId": "browser",
+            "bucket": {
+                "start": "2024-01-01T00:00:00Z",
+                "stop": "2024-01-01T00:01:00Z",
+                "toggles": {
+                    "default": {"yes": 3, "no": 1, "variants": {"disabled": 2}},
+                    "unenumerated": {"yes": 5, "no": 0},
+                    "nonexistent": {"yes": 1, "no": 1}
+                }
+            }
+        }"#;
+        let request = Request::post("/api/frontend/client/metrics")
+            .body(body.to_vec())
+            .unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::ACCEPTED);
+
+        let metrics = api
+            .client
+            .memoize(features())
+            .unwrap()
+            .expect("metrics from the previous cache");
+        let default = metrics.bucket.toggles.get("default").unwrap();
+        assert_eq!(default.yes, 3);
+        assert_eq!(default.no, 1);
+        assert_eq!(*default.variants.get("disabled").unwrap(), 2);
+        assert_eq!(metrics.bucket.toggles.get("unenumerated").unwrap().yes, 5);
+        assert!(!metrics.bucket.toggles.contains_key("nonexistent"));
+    }
+
+    #[test]
+    fn token_validation() {
+        let api = api().token_validator(Box::new(|token| token == "secret"));
+        let request = Request::get("/api/frontend").body(vec![]).unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::UNAUTHORIZED);
+        let request = Request::get("/api/frontend")
+            .header("authorization", "wrong")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::UNAUTHORIZED);
+        let request = Request::get("/api/frontend")
+            .header("authorization", "secret")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn routing() {
+        let api = api().base_path("/proxy/");
+        let request = Request::get("/api/frontend").body(vec![]).unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::NOT_FOUND);
+        let request = Request::get("/proxy").body(vec![]).unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::OK);
+        let request = Request::delete("/proxy").body(vec![]).unwrap();
+        assert_eq!(
+            api.handle(&request).status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        let request = Request::get("/proxy/client/features").body(vec![]).unwrap();
+        assert_eq!(api.handle(&request).status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -65,20 +65,62 @@ where
     }
 
     /// Make a get request and parse into JSON
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "unleash.http",
+            skip_all,
+            fields(method = "GET", endpoint = %endpoint, duration_ms = tracing::field::Empty, status = tracing::field::Empty)
+        )
+    )]
     pub async fn get_json<T: DeserializeOwned>(
         &self,
         endpoint: &str,
         interval: Option<u64>,
     ) -> Result<T, C::Error> {
-        let mut request = self.get(endpoint);
-        if let Some(interval) = interval {
-            request = C::header(
-                request,
-                &C::build_header("unleash-interval")?,
-                &interval.to_string(),
-            );
+        let request = self.with_interval(self.get(endpoint), interval)?;
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let result = C::get_json(request).await;
+        #[cfg(feature = "tracing")]
+        record_outcome(started, if result.is_ok() { "ok" } else { "error" });
+        result
+    }
+
+    /// Make a get request and parse into JSON, along with the response's
+    /// `ETag`, or `None` if the response is 304 Not Modified. `etag`, from an
+    /// earlier response, is sent as `If-None-Match`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "unleash.http",
+            skip_all,
+            fields(method = "GET", endpoint = %endpoint, duration_ms = tracing::field::Empty, status = tracing::field::Empty)
+        )
+    )]
+    pub async fn get_json_if_modified<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        interval: Option<u64>,
+        etag: Option<&str>,
+    ) -> Result<Option<(T, Option<String>)>, C::Error> {
+        let mut request = self.with_interval(self.get(endpoint), interval)?;
+        if let Some(etag) = etag {
+            request = C::header(request, &C::build_header("if-none-match")?, etag);
         }
-        C::get_json(request).await
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let result = C::get_json_if_modified(request).await;
+        #[cfg(feature = "tracing")]
+        record_outcome(
+            started,
+            match result {
+                Ok(Some(_)) => "ok",
+                Ok(None) => "not_modified",
+                Err(_) => "error",
+            },
+        );
+        result
     }
 
     /// Perform a POST. Returns errors per HttpClient::post.
@@ -89,21 +131,49 @@ where
 
     /// Encode content into JSON and post to an endpoint. Returns the statuscode
     /// is_success() value.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "unleash.http",
+            skip_all,
+            fields(method = "POST", endpoint = %endpoint, duration_ms = tracing::field::Empty, status = tracing::field::Empty)
+        )
+    )]
     pub async fn post_json<T: Serialize + Sync>(
         &self,
         endpoint: &str,
         content: T,
         interval: Option<u64>,
     ) -> Result<bool, C::Error> {
-        let mut request = self.post(endpoint);
-        if let Some(interval) = interval {
-            request = C::header(
+        let request = self.with_interval(self.post(endpoint), interval)?;
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let result = C::post_json(request, &content).await;
+        #[cfg(feature = "tracing")]
+        record_outcome(
+            started,
+            match result {
+                Ok(true) => "ok",
+                Ok(false) => "rejected",
+                Err(_) => "error",
+            },
+        );
+        result
+    }
+
+    fn with_interval(
+        &self,
+        request: C::RequestBuilder,
+        interval: Option<u64>,
+    ) -> Result<C::RequestBuilder, C::Error> {
+        match interval {
+            Some(interval) => Ok(C::header(
                 request,
                 &C::build_header("unleash-interval")?,
                 &interval.to_string(),
-            );
+            )),
+            None => Ok(request),
         }
-        C::post_json(request, &content).await
     }
 
     fn attach_headers(&self, request: C::RequestBuilder) -> C::RequestBuilder {
@@ -128,6 +198,14 @@ where
     }
 }
 
+/// Record how a request went on the current `unleash.http` span.
+#[cfg(feature = "tracing")]
+fn record_outcome(started: std::time::Instant, status: &str) {
+    let span = tracing::Span::current();
+    span.record("duration_ms", started.elapsed().as_millis() as u64);
+    span.record("status", status);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -219,4 +297,74 @@ mod tests {
             "Connection ID is not a valid UUID"
         );
     }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_request_spans() {
+        use std::fmt::Debug;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+        use tracing_core::span::Current;
+
+        // Collects the fields recorded on the (single) span
+        #[derive(Clone, Default)]
+        struct Collector(
+            Arc<RwLock<Vec<(String, String)>>>,
+            Arc<RwLock<Option<&'static Metadata<'static>>>>,
+        );
+
+        impl Visit for Collector {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0
+                    .write()
+                    .unwrap()
+                    .push((field.name().into(), format!("{value:?}")));
+            }
+        }
+
+        impl tracing::Subscriber for Collector {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                span.record(&mut self.clone());
+                *self.1.write().unwrap() = Some(span.metadata());
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, values: &Record<'_>) {
+                values.record(&mut self.clone());
+            }
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+            fn current_span(&self) -> Current {
+                match *self.1.read().unwrap() {
+                    Some(metadata) => Current::new(Id::from_u64(1), metadata),
+                    None => Current::none(),
+                }
+            }
+        }
+
+        let collector = Collector::default();
+        let _guard = tracing::subscriber::set_default(collector.clone());
+        let http_client = HTTP::<MockHttpClient>::new(
+            "my_app".to_string(),
+            "my_instance_id".to_string(),
+            "d512f8ec-d972-40a5-9a30-a0a6e85d93ac".to_string(),
+            None,
+        )
+        .unwrap();
+        http_client
+            .post_json("http://example.com/client/metrics", (), None)
+            .await
+            .unwrap();
+
+        let fields: HashMap<_, _> = collector.0.read().unwrap().iter().cloned().collect();
+        assert_eq!(fields["method"], "\"POST\"");
+        assert_eq!(fields["endpoint"], "http://example.com/client/metrics");
+        assert_eq!(fields["status"], "\"ok\"");
+        assert!(fields.contains_key("duration_ms"));
+    }
 }
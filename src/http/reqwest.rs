@@ -37,6 +37,21 @@ impl HttpClient for reqwest::Client {
         req.send().await?.json::<T>().await
     }
 
+    async fn get_json_if_modified<T: DeserializeOwned>(
+        req: Self::RequestBuilder,
+    ) -> Result<Option<(T, Option<String>)>, Self::Error> {
+        let res = req.send().await?;
+        if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let etag = res
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(Some((res.json::<T>().await?, etag)))
+    }
+
     async fn post_json<T: Serialize + Sync>(
         req: Self::RequestBuilder,
         content: &T,
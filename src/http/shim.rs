@@ -6,10 +6,17 @@
 
 use core::fmt::{Debug, Display};
 use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
 
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
 
+// The future of an async_trait method
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+// A response body and its ETag, or None for 304 Not Modified
+type IfModified<T, E> = Result<Option<(T, Option<String>)>, E>;
+
 /// Abstraction over the concrete HTTP client being used. Implement this on any
 /// type to use it as an HTTP client.
 #[async_trait]
@@ -37,6 +44,23 @@ pub trait HttpClient: Sync + Send {
     /// Make a get request and parse into JSON
     async fn get_json<T: DeserializeOwned>(req: Self::RequestBuilder) -> Result<T, Self::Error>;
 
+    /// Make a get request and parse into JSON, along with the response's
+    /// `ETag` header, or `None` if the response is 304 Not Modified. The
+    /// default implementation treats every response as modified and without
+    /// an `ETag`.
+    // Not an async fn: the request is handed to get_json up front, so the
+    // default future need not hold (and require Send of) the builder.
+    fn get_json_if_modified<'async_trait, T>(
+        req: Self::RequestBuilder,
+    ) -> BoxFuture<'async_trait, IfModified<T, Self::Error>>
+    where
+        T: DeserializeOwned + 'async_trait,
+        Self: 'async_trait,
+    {
+        let response = Self::get_json::<T>(req);
+        Box::pin(async move { response.await.map(|value| Some((value, None))) })
+    }
+
     /// Encode content into JSON and post to an endpoint. Returns the statuscode
     /// is_success() value.
     async fn post_json<T: Serialize + Sync>(
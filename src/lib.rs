@@ -98,10 +98,24 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
 
 * **backtrace** -
   Enable backtrace feature in anyhow (nightly only)
+* **cli** -
+  The `dump-features`, `evaluate-features` and `generate-features` binaries,
+  and `codegen` used by the latter.
 * **default** -
   By default no features are enabled.
+* **derive** -
+  The `FeatureEnum` derive macro for feature enums.
+* **frontend** -
+  Serve the Unleash frontend API from a client's cached state.
 * **functional** -
   Only relevant to developers: enables the functional test suite.
+* **open-feature** -
+  An OpenFeature provider backed by a `Client`, see `openfeature`.
+* **openmetrics** -
+  Render toggle usage and client health as OpenMetrics (Prometheus) text.
+* **opentelemetry** -
+  OpenTelemetry `feature_flag` span events for evaluations of selected
+  toggles, see `ClientBuilder::feature_flag_events`.
 * **reqwest-client** -
   Enables reqwest with OpenSSL TLS support
 * **reqwest-client-11** -
@@ -112,22 +126,64 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
   Enables reqwest 0.11 with RusTLS support
 * **strict** -
   Turn unexpected fields in API responses into errors
+* **test-support** -
+  Test doubles in `testing`: a fake client for code that evaluates
+  features, and a mock Unleash server for testing the client end to end.
+* **tokio** -
+  `context::TaskLocalContext`, a context provider for async code built on
+  tokio's task local storage (any executor can poll it).
+* **tower** -
+  Tower middleware extracting the evaluation context from requests, and
+  scoping it as the `TaskLocalContext` for handlers (implies **tokio**).
+* **tracing** -
+  `tracing` spans for polling, registration, memoization and HTTP requests,
+  with the feature count, duration and status as fields, and trace level
+  spans for evaluations.
 */
 #![warn(clippy::all)]
 
 pub mod api;
 pub mod client;
+pub mod clock;
+#[cfg(feature = "cli")]
+pub mod codegen;
 pub mod config;
 pub mod context;
+pub mod explain;
+#[cfg(feature = "frontend")]
+pub mod frontend;
 pub mod http;
+#[cfg(feature = "tower")]
+pub mod middleware;
+#[cfg(feature = "open-feature")]
+pub mod openfeature;
+#[cfg(feature = "openmetrics")]
+pub mod openmetrics;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+pub mod overrides;
+pub mod random;
+pub mod redact;
 pub mod strategy;
+#[cfg(feature = "test-support")]
+pub mod testing;
 pub mod version;
+mod watch;
 
 // Exports for ergonomical use
-pub use crate::client::{Client, ClientBuilder};
+pub use crate::client::{Client, ClientBuilder, FeatureDefaults, Toggles};
 pub use crate::config::EnvironmentConfig;
 pub use crate::context::Context;
 pub use crate::strategy::Evaluate;
+#[cfg(feature = "derive")]
+pub use unleash_api_client_derive::FeatureEnum;
+
+// Paths used by code generated by the derive macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use enum_map;
+    pub use serde;
+}
 
 /// For the complete minimalist
 ///
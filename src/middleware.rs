@@ -0,0 +1,446 @@
+//! Tower middleware that extracts an Unleash [`Context`] from each request.
+//!
+//! [`UnleashLayer`] wraps a service so that every request reaching it carries,
+//! as request extensions, the [`Context`] derived from the request and the
+//! shared [`Client`]. Handlers can then evaluate toggles without rebuilding
+//! the context themselves; in axum, for instance, both are available through
+//! the `Extension` extractor.
+//!
+//! Where each context field comes from is set with a [`ContextExtractor`]:
+//!
+//! - `userId` from a header, or from a custom function (e.g. reading a claim
+//!   that authentication middleware stored in the extensions).
+//! - `sessionId` from a cookie.
+//! - `remoteAddress` from the peer address, or optionally the first
+//!   `X-Forwarded-For` entry when running behind a trusted proxy.
+//!
+//! The inner service also runs in a [`TaskLocalContext`] scope holding the
+//! context, so a client built with
+//! `.context_provider(Arc::new(TaskLocalContext))` evaluates against the
+//! current request when `is_enabled` is called without a context, even from
+//! code that has no access to the request.
+use std::fmt::Debug;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use enum_map::EnumArray;
+use http::header::{HeaderName, COOKIE};
+use http::Request;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::task::futures::TaskLocalFuture;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::client::{CachedFeature, Client};
+use crate::context::{Context, IPAddress, TaskLocalContext};
+use crate::http::HttpClient;
+
+/// Read a context field from the request head.
+pub type FieldExtractor<T> = Box<dyn Fn(&http::request::Parts) -> Option<T> + Sync + Send>;
+
+/// Configures how a [`Context`] is extracted from a request.
+///
+/// By default only `remoteAddress` is set, from a [`SocketAddr`] request
+/// extension (as inserted by many servers for the peer address).
+#[derive(Default)]
+pub struct ContextExtractor {
+    user_id_header: Option<HeaderName>,
+    user_id_fn: Option<FieldExtractor<String>>,
+    session_cookie: Option<String>,
+    peer_address_fn: Option<FieldExtractor<IpAddr>>,
+    forwarded_for: bool,
+}
+
+impl ContextExtractor {
+    /// Take `userId` from the value of `header`.
+    pub fn user_id_header(mut self, header: HeaderName) -> Self {
+        self.user_id_header = Some(header);
+        self
+    }
+
+    /// Take `userId` from a function of the request head, e.g. to read a
+    /// claim from an authenticated token. Consulted before the header.
+    pub fn user_id_with(mut self, extractor: FieldExtractor<String>) -> Self {
+        self.user_id_fn = Some(extractor);
+        self
+    }
+
+    /// Take `sessionId` from the cookie called `name`.
+    pub fn session_cookie(mut self, name: &str) -> Self {
+        self.session_cookie = Some(name.into());
+        self
+    }
+
+    /// Find the peer address with a function of the request head, for servers
+    /// that do not insert a [`SocketAddr`] extension (e.g. axum's
+    /// `ConnectInfo`).
+    pub fn peer_address_with(mut self, extractor: FieldExtractor<IpAddr>) -> Self {
+        self.peer_address_fn = Some(extractor);
+        self
+    }
+
+    /// Prefer the first `X-Forwarded-For` address over the peer address. Only
+    /// enable this behind a proxy that sets the header, as clients can forge
+    /// it.
+    pub fn forwarded_for(mut self, enabled: bool) -> Self {
+        self.forwarded_for = enabled;
+        self
+    }
+
+    /// Extract the context for a request head.
+    pub fn extract(&self, parts: &http::request::Parts) -> Context {
+        let user_id = self
+            .user_id_fn
+            .as_ref()
+            .and_then(|extract| extract(parts))
+            .or_else(|| {
+                self.user_id_header
+                    .as_ref()
+                    .and_then(|header| parts.headers.get(header))
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            });
+        let session_id = self
+            .session_cookie
+            .as_ref()
+            .and_then(|name| cookie(parts, name));
+        let forwarded = if self.forwarded_for {
+            forwarded_for(parts)
+        } else {
+            None
+        };
+        let remote_address = forwarded.or_else(|| match &self.peer_address_fn {
+            Some(extract) => extract(parts),
+            None => parts.extensions.get::<SocketAddr>().map(SocketAddr::ip),
+        });
+        Context {
+            user_id,
+            session_id,
+            remote_address: remote_address.map(IPAddress),
+            ..Default::default()
+        }
+    }
+}
+
+fn cookie(parts: &http::request::Parts, name: &str) -> Option<String> {
+    parts
+        .headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"').to_string())
+}
+
+fn forwarded_for(parts: &http::request::Parts) -> Option<IpAddr> {
+    parts
+        .headers
+        .get("x-forwarded-for")?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// A [`Layer`] adding the request [`Context`] and the [`Client`] to request
+/// extensions.
+pub struct UnleashLayer<F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    client: Arc<Client<F, C>>,
+    extractor: Arc<ContextExtractor>,
+}
+
+impl<F, C> UnleashLayer<F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    pub fn new(client: Arc<Client<F, C>>, extractor: ContextExtractor) -> Self {
+        Self {
+            client,
+            extractor: Arc::new(extractor),
+        }
+    }
+}
+
+impl<F, C> Clone for UnleashLayer<F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            extractor: self.extractor.clone(),
+        }
+    }
+}
+
+impl<S, F, C> Layer<S> for UnleashLayer<F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    type Service = UnleashService<S, F, C>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UnleashService {
+            inner,
+            client: self.client.clone(),
+            extractor: self.extractor.clone(),
+        }
+    }
+}
+
+/// The service produced by [`UnleashLayer`].
+pub struct UnleashService<S, F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    inner: S,
+    client: Arc<Client<F, C>>,
+    extractor: Arc<ContextExtractor>,
+}
+
+impl<S, F, C> Clone for UnleashService<S, F, C>
+where
+    S: Clone,
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            client: self.client.clone(),
+            extractor: self.extractor.clone(),
+        }
+    }
+}
+
+impl<S, F, C, B> Service<Request<B>> for UnleashService<S, F, C>
+where
+    S: Service<Request<B>>,
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize + 'static,
+    C: HttpClient + 'static,
+    Client<F, C>: Send + Sync,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TaskLocalFuture<Context, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let (mut parts, body) = request.into_parts();
+        let context = self.extractor.extract(&parts);
+        parts.extensions.insert(context.clone());
+        parts.extensions.insert(self.client.clone());
+        let request = Request::from_parts(parts, body);
+        let future = TaskLocalContext::sync_scope(context.clone(), || self.inner.call(request));
+        TaskLocalContext::scope(context, future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::future::{ready, Future, Ready};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::task::{Context as TaskContext, Poll};
+
+    use enum_map::Enum;
+    use http::header::HeaderName;
+    use http::Request;
+    use maplit::hashmap;
+    use serde::{Deserialize, Serialize};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    use super::{ContextExtractor, UnleashLayer};
+    use crate::api::{Feature, Strategy};
+    use crate::client::{Client, ClientBuilder};
+    use crate::context::{Context, IPAddress, TaskLocalContext};
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "reqwest")] {
+            use reqwest::Client as HttpClient;
+        } else if #[cfg(feature = "reqwest-11")] {
+            use reqwest_11::Client as HttpClient;
+        }
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    enum UserFeatures {
+        default,
+    }
+
+    /// Echoes the extensions the layer added.
+    #[derive(Clone)]
+    struct Capture;
+
+    impl<B> Service<Request<B>> for Capture {
+        type Response = (Option<Context>, bool);
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<B>) -> Self::Future {
+            let extensions = request.extensions();
+            ready(Ok((
+                extensions.get::<Context>().cloned(),
+                extensions
+                    .get::<Arc<Client<UserFeatures, HttpClient>>>()
+                    .is_some(),
+            )))
+        }
+    }
+
+    fn call(extractor: ContextExtractor, request: Request<()>) -> (Option<Context>, bool) {
+        let client = ClientBuilder::default()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        let mut service = UnleashLayer::new(Arc::new(client), extractor).layer(Capture);
+        futures::executor::block_on(service.call(request)).unwrap()
+    }
+
+    #[test]
+    fn extracts_context() {
+        let extractor = ContextExtractor::default()
+            .user_id_header(HeaderName::from_static("x-user-id"))
+            .session_cookie("session");
+        let mut request = Request::builder()
+            .header("x-user-id", "fred")
+            .header("cookie", "theme=dark; session=\"abc123\"")
+            .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+            .body(())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert("10.0.0.1:4242".parse::<SocketAddr>().unwrap());
+        let (context, has_client) = call(extractor, request);
+        let context = context.unwrap();
+        assert!(has_client);
+        assert_eq!(context.user_id.as_deref(), Some("fred"));
+        assert_eq!(context.session_id.as_deref(), Some("abc123"));
+        // X-Forwarded-For is not trusted by default
+        assert_eq!(
+            context.remote_address,
+            Some(IPAddress("10.0.0.1".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn forwarded_for_and_custom_extractors() {
+        let extractor = ContextExtractor::default()
+            .forwarded_for(true)
+            .user_id_header(HeaderName::from_static("x-user-id"))
+            .user_id_with(Box::new(|parts| {
+                parts.extensions.get::<&str>().map(|s| s.to_string())
+            }));
+        let mut request = Request::builder()
+            .header("x-user-id", "fred")
+            .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+            .body(())
+            .unwrap();
+        request.extensions_mut().insert("claimed");
+        let context = call(extractor, request).0.unwrap();
+        assert_eq!(context.user_id.as_deref(), Some("claimed"));
+        assert_eq!(
+            context.remote_address,
+            Some(IPAddress("203.0.113.7".parse().unwrap()))
+        );
+
+        // Nothing configured and nothing available: an empty context
+        let context = call(
+            ContextExtractor::default(),
+            Request::builder().body(()).unwrap(),
+        )
+        .0
+        .unwrap();
+        assert_eq!(context, Context::default());
+    }
+
+    /// Evaluates without passing a context, as code far from the request
+    /// would.
+    struct Handler(Arc<Client<UserFeatures, HttpClient>>);
+
+    impl<B> Service<Request<B>> for Handler {
+        type Response = (bool, bool);
+        type Error = Infallible;
+        type Future = std::pin::Pin<Box<dyn Future<Output = Result<(bool, bool), Infallible>>>>;
+
+        fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<B>) -> Self::Future {
+            let client = self.0.clone();
+            let in_call = client.is_enabled(UserFeatures::default, None, false);
+            Box::pin(async move {
+                futures::future::ready(()).await;
+                Ok((
+                    in_call,
+                    client.is_enabled(UserFeatures::default, None, false),
+                ))
+            })
+        }
+    }
+
+    #[test]
+    fn handlers_see_the_request_context() {
+        let client = ClientBuilder::default()
+            .context_provider(Arc::new(TaskLocalContext))
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                variants: None,
+                name: "default".into(),
+                strategies: vec![Strategy {
+                    name: "userWithId".into(),
+                    parameters: Some(hashmap!["userIds".into()=>"fred".into()]),
+                    ..Default::default()
+                }],
+            }])
+            .unwrap();
+        let client = Arc::new(client);
+        let extractor =
+            ContextExtractor::default().user_id_header(HeaderName::from_static("x-user-id"));
+        let mut service = UnleashLayer::new(client.clone(), extractor).layer(Handler(client));
+        let request = |user| {
+            Request::builder()
+                .header("x-user-id", user)
+                .body(())
+                .unwrap()
+        };
+        assert_eq!(
+            futures::executor::block_on(service.call(request("fred"))).unwrap(),
+            (true, true)
+        );
+        assert_eq!(
+            futures::executor::block_on(service.call(request("barney"))).unwrap(),
+            (false, false)
+        );
+    }
+}
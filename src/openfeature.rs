@@ -0,0 +1,481 @@
+//! An [OpenFeature](https://openfeature.dev) provider backed by a `Client`.
+//!
+//! [`UnleashProvider`] resolves flags by toggle name, through the feature enum
+//! for toggles in it and as string features otherwise. The client should be
+//! polling for updates, and have string features enabled
+//! (`ClientBuilder::enable_string_features`) for the metrics of toggles
+//! outside the enum to be sent:
+//!
+//! - boolean flags evaluate the toggle, as `Client::is_enabled`;
+//! - string flags select a variant, as `Client::get_variant`, resolving to
+//!   its payload value, or to its name when it has no payload;
+//! - integer and float flags resolve to a `number` payload, and object flags
+//!   to a `json` payload holding a JSON object.
+//!
+//! The evaluation context's targeting key becomes the user id. Custom fields
+//! named after context fields (`userId`, `sessionId`, `remoteAddress`,
+//! `appName`, `environment`, `currentTime`) set them; any other field becomes
+//! a property.
+//!
+//! Reasons: `STATIC` for toggles decided by a local override, `DISABLED` for
+//! toggles turned off, `TARGETING_MATCH` or `DEFAULT` for toggles that are on
+//! depending on whether a strategy matched, and `SPLIT` for a selected
+//! variant. Errors (on which OpenFeature falls back to the default value):
+//! `PROVIDER_NOT_READY` before toggles are first fetched, `FLAG_NOT_FOUND` for
+//! unknown toggles, `TYPE_MISMATCH` when the variant has no payload of the
+//! requested type, `PARSE_ERROR` for malformed payloads and `INVALID_CONTEXT`
+//! for context fields that cannot be converted.
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use enum_map::EnumArray;
+use open_feature::provider::{
+    FeatureProvider, ProviderMetadata, ProviderStatus, ResolutionDetails,
+};
+use open_feature::{
+    EvaluationContext, EvaluationContextFieldValue, EvaluationError, EvaluationErrorCode,
+    EvaluationReason, EvaluationResult, StructValue, Value,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::client::{CachedFeature, Client, Variant};
+use crate::context::{Context, IPAddress, PropertyValue};
+use crate::http::HttpClient;
+
+/// The name the provider reports in its metadata.
+pub const PROVIDER_NAME: &str = "Unleash";
+
+/// An OpenFeature provider evaluating flags with a `Client`.
+///
+/// Register it with OpenFeature as any other provider, e.g.
+/// `OpenFeature::singleton_mut().await.set_provider(UnleashProvider::new(client))`.
+pub struct UnleashProvider<F, C>
+where
+    F: EnumArray<CachedFeature> + Debug + DeserializeOwned + Serialize,
+    C: HttpClient,
+{
+    client: Arc<Client<F, C>>,
+    metadata: ProviderMetadata,
+}
+
+impl<F, C> UnleashProvider<F, C>
+where
+    F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
+    C: HttpClient + Default,
+{
+    /// Resolve flags with `client`.
+    pub fn new(client: Arc<Client<F, C>>) -> Self {
+        Self {
+            client,
+            metadata: ProviderMetadata::new(PROVIDER_NAME),
+        }
+    }
+
+    /// The client flags are resolved with.
+    pub fn client(&self) -> &Arc<Client<F, C>> {
+        &self.client
+    }
+
+    /// The reason for the toggle's result when it is decided before
+    /// evaluation, or the error preventing evaluation.
+    fn check(&self, flag_key: &str) -> EvaluationResult<Option<EvaluationReason>> {
+        if self.client.overrides().get(flag_key).is_some() {
+            return Ok(Some(EvaluationReason::Static));
+        }
+        let cache = self.client.cached_state();
+        let Some(cache) = cache.as_ref() else {
+            return Err(error(
+                EvaluationErrorCode::ProviderNotReady,
+                "no toggles have been fetched yet",
+            ));
+        };
+        match cache.feature(flag_key) {
+            Some(feature) if feature.known() => Ok(feature
+                .is_feature_disabled()
+                .then_some(EvaluationReason::Disabled)),
+            _ => Err(error(
+                EvaluationErrorCode::FlagNotFound,
+                format!("unknown toggle {flag_key:?}"),
+            )),
+        }
+    }
+
+    /// Select the variant for a flag, with the reason for it.
+    fn variant(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<(Variant, EvaluationReason)> {
+        let context = to_context(evaluation_context)?;
+        let reason = self.check(flag_key)?;
+        let variant = self.client.get_variant_by_name(flag_key, &context);
+        let reason = reason.unwrap_or(if variant.enabled {
+            EvaluationReason::Split
+        } else {
+            EvaluationReason::Default
+        });
+        Ok((variant, reason))
+    }
+
+    /// Resolve a flag to the variant's payload of type `kind`, parsed with
+    /// `parse`.
+    fn payload<T>(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+        kind: &str,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> EvaluationResult<ResolutionDetails<T>> {
+        let (variant, reason) = self.variant(flag_key, evaluation_context)?;
+        if variant.payload.get("type").map(String::as_str) != Some(kind) {
+            return Err(error(
+                EvaluationErrorCode::TypeMismatch,
+                format!("variant {:?} has no {kind} payload", variant.name),
+            ));
+        }
+        let value = variant.payload.get("value").map(String::as_str);
+        let Some(value) = value.and_then(parse) else {
+            return Err(error(
+                EvaluationErrorCode::ParseError,
+                format!("variant {:?} has a malformed {kind} payload", variant.name),
+            ));
+        };
+        Ok(details(value, variant.name, reason))
+    }
+}
+
+#[async_trait]
+impl<F, C> FeatureProvider for UnleashProvider<F, C>
+where
+    F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
+    C: HttpClient + Default,
+    Client<F, C>: Send + Sync + 'static,
+{
+    /// Ready once toggles have been fetched.
+    fn status(&self) -> ProviderStatus {
+        if self.client.cached_state().is_some() {
+            ProviderStatus::Ready
+        } else {
+            ProviderStatus::NotReady
+        }
+    }
+
+    fn metadata(&self) -> &ProviderMetadata {
+        &self.metadata
+    }
+
+    async fn resolve_bool_value(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<ResolutionDetails<bool>> {
+        let context = to_context(evaluation_context)?;
+        let reason = self.check(flag_key)?;
+        let enabled = self
+            .client
+            .is_enabled_by_name(flag_key, Some(&context), false);
+        let reason = reason.unwrap_or(if enabled {
+            EvaluationReason::TargetingMatch
+        } else {
+            EvaluationReason::Default
+        });
+        let variant = if enabled { "on" } else { "off" };
+        Ok(details(enabled, variant.into(), reason))
+    }
+
+    async fn resolve_int_value(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<ResolutionDetails<i64>> {
+        self.payload(flag_key, evaluation_context, "number", |value| {
+            value.trim().parse().ok()
+        })
+    }
+
+    async fn resolve_float_value(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<ResolutionDetails<f64>> {
+        self.payload(flag_key, evaluation_context, "number", |value| {
+            value.trim().parse().ok()
+        })
+    }
+
+    async fn resolve_string_value(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<ResolutionDetails<String>> {
+        let (mut variant, reason) = self.variant(flag_key, evaluation_context)?;
+        let value = variant
+            .payload
+            .remove("value")
+            .unwrap_or_else(|| variant.name.clone());
+        Ok(details(value, variant.name, reason))
+    }
+
+    async fn resolve_struct_value(
+        &self,
+        flag_key: &str,
+        evaluation_context: &EvaluationContext,
+    ) -> EvaluationResult<ResolutionDetails<StructValue>> {
+        self.payload(flag_key, evaluation_context, "json", |value| {
+            let json: serde_json::Value = serde_json::from_str(value).ok()?;
+            match Value::try_from(json).ok()? {
+                Value::Struct(value) => Some(value),
+                _ => None,
+            }
+        })
+    }
+}
+
+fn details<T>(value: T, variant: String, reason: EvaluationReason) -> ResolutionDetails<T> {
+    ResolutionDetails {
+        value,
+        variant: Some(variant),
+        reason: Some(reason),
+        flag_metadata: None,
+    }
+}
+
+fn error(code: EvaluationErrorCode, message: impl Into<String>) -> EvaluationError {
+    EvaluationError {
+        code,
+        message: Some(message.into()),
+    }
+}
+
+/// Convert an OpenFeature evaluation context to an Unleash one.
+pub fn to_context(evaluation_context: &EvaluationContext) -> EvaluationResult<Context> {
+    let invalid = |field: &str| {
+        error(
+            EvaluationErrorCode::InvalidContext,
+            format!("unsupported value for context field {field:?}"),
+        )
+    };
+    let mut context = Context {
+        user_id: evaluation_context.targeting_key.clone(),
+        ..Default::default()
+    };
+    for (name, value) in &evaluation_context.custom_fields {
+        let value = match value {
+            EvaluationContextFieldValue::Bool(value) => PropertyValue::Bool(*value),
+            EvaluationContextFieldValue::Int(value) => PropertyValue::Number(*value as f64),
+            EvaluationContextFieldValue::Float(value) => PropertyValue::Number(*value),
+            EvaluationContextFieldValue::String(value) => PropertyValue::String(value.clone()),
+            EvaluationContextFieldValue::DateTime(value) => {
+                chrono::DateTime::from_timestamp(value.unix_timestamp(), value.nanosecond())
+                    .map(PropertyValue::Date)
+                    .ok_or_else(|| invalid(name))?
+            }
+            EvaluationContextFieldValue::Struct(_) => return Err(invalid(name)),
+        };
+        match name.as_str() {
+            "userId" => context.user_id = Some(value.as_str().into_owned()),
+            "sessionId" => context.session_id = Some(value.as_str().into_owned()),
+            "remoteAddress" => {
+                let address = value.as_str().parse().map_err(|_| invalid(name))?;
+                context.remote_address = Some(IPAddress(address));
+            }
+            "appName" => context.app_name = value.as_str().into_owned(),
+            "environment" => context.environment = value.as_str().into_owned(),
+            "currentTime" => {
+                context.current_time = Some(value.as_date().ok_or_else(|| invalid(name))?)
+            }
+            _ => {
+                context.properties.insert(name.clone(), value);
+            }
+        }
+    }
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use enum_map::Enum;
+    use maplit::hashmap;
+    use open_feature::provider::FeatureProvider;
+    use open_feature::{EvaluationContext, EvaluationErrorCode, EvaluationReason, StructValue};
+    use serde::{Deserialize, Serialize};
+
+    use super::{to_context, UnleashProvider};
+    use crate::api::{Constraint, ConstraintExpression, Feature, Strategy, Variant};
+    use crate::client::ClientBuilder;
+    use crate::context::PropertyValue;
+    use crate::overrides::Override;
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "reqwest")] {
+            use reqwest::Client as HttpClient;
+        } else if #[cfg(feature = "reqwest-11")] {
+            use reqwest_11::Client as HttpClient;
+        }
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    enum UserFeatures {
+        flag,
+        #[serde(rename = "new.text")]
+        text,
+        unused,
+    }
+
+    fn feature(name: &str, enabled: bool, payload: Option<(&str, &str)>) -> Feature {
+        Feature {
+            description: None,
+            enabled,
+            created_at: None,
+            name: name.into(),
+            variants: payload.map(|(kind, value)| {
+                vec![Variant {
+                    name: "only".into(),
+                    weight: 100,
+                    payload: Some(hashmap! {
+                        "type".into() => kind.into(),
+                        "value".into() => value.into(),
+                    }),
+                    overrides: None,
+                }]
+            }),
+            strategies: vec![Strategy {
+                name: "default".into(),
+                constraints: Some(vec![Constraint {
+                    context_name: "plan".into(),
+                    case_insensitive: false,
+                    inverted: false,
+                    expression: ConstraintExpression::In {
+                        values: vec!["pro".into()],
+                    },
+                }]),
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves() {
+        let client = ClientBuilder::default()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        let provider = UnleashProvider::new(Arc::new(client));
+        let pro = EvaluationContext::default()
+            .with_targeting_key("fred")
+            .with_custom_field("plan", "pro");
+        let none = EvaluationContext::default();
+
+        let err = provider.resolve_bool_value("flag", &pro).await.unwrap_err();
+        assert_eq!(err.code, EvaluationErrorCode::ProviderNotReady);
+
+        provider
+            .client()
+            .memoize(vec![
+                feature("flag", true, None),
+                feature("off", false, None),
+                feature("text", true, Some(("string", "hello"))),
+                feature("count", true, Some(("number", "42"))),
+                feature("config", true, Some(("json", r#"{"size": 3}"#))),
+                feature("new.text", true, Some(("string", "renamed"))),
+            ])
+            .unwrap();
+
+        let flag = provider.resolve_bool_value("flag", &pro).await.unwrap();
+        assert!(flag.value);
+        assert_eq!(flag.reason, Some(EvaluationReason::TargetingMatch));
+        let flag = provider.resolve_bool_value("flag", &none).await.unwrap();
+        assert!(!flag.value);
+        assert_eq!(flag.reason, Some(EvaluationReason::Default));
+        let off = provider.resolve_bool_value("off", &pro).await.unwrap();
+        assert_eq!(off.reason, Some(EvaluationReason::Disabled));
+        let err = provider
+            .resolve_bool_value("missing", &pro)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, EvaluationErrorCode::FlagNotFound);
+
+        let text = provider.resolve_string_value("text", &pro).await.unwrap();
+        assert_eq!(text.value, "hello");
+        assert_eq!(text.variant.as_deref(), Some("only"));
+        assert_eq!(text.reason, Some(EvaluationReason::Split));
+        let text = provider.resolve_string_value("flag", &pro).await.unwrap();
+        assert_eq!(text.value, "disabled");
+        assert_eq!(
+            provider
+                .resolve_int_value("count", &pro)
+                .await
+                .unwrap()
+                .value,
+            42
+        );
+        assert_eq!(
+            provider
+                .resolve_float_value("count", &pro)
+                .await
+                .unwrap()
+                .value,
+            42.0
+        );
+        let err = provider.resolve_int_value("text", &pro).await.unwrap_err();
+        assert_eq!(err.code, EvaluationErrorCode::TypeMismatch);
+        assert_eq!(
+            provider
+                .resolve_struct_value("config", &pro)
+                .await
+                .unwrap()
+                .value,
+            StructValue::default().with_field("size", 3)
+        );
+
+        // Enumerated and unenumerated toggles alike, by toggle name
+        let text = provider
+            .resolve_string_value("new.text", &pro)
+            .await
+            .unwrap();
+        assert_eq!(text.value, "renamed");
+        let err = provider
+            .resolve_bool_value("unused", &pro)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, EvaluationErrorCode::FlagNotFound);
+        let metrics = provider.client().metrics();
+        let flag = &metrics.toggles["flag"];
+        assert_eq!((flag.yes, flag.no), (2, 1));
+        assert_eq!(metrics.toggles["new.text"].variants["only"], 1);
+
+        provider
+            .client()
+            .overrides()
+            .set("off", Override::enabled(true));
+        let off = provider.resolve_bool_value("off", &none).await.unwrap();
+        assert!(off.value);
+        assert_eq!(off.reason, Some(EvaluationReason::Static));
+    }
+
+    #[test]
+    fn converts_contexts() {
+        let context = to_context(
+            &EvaluationContext::default()
+                .with_targeting_key("fred")
+                .with_custom_field("sessionId", "abc")
+                .with_custom_field("remoteAddress", "10.0.0.1")
+                .with_custom_field("seats", 25),
+        )
+        .unwrap();
+        assert_eq!(context.user_id.as_deref(), Some("fred"));
+        assert_eq!(context.session_id.as_deref(), Some("abc"));
+        assert_eq!(
+            context.remote_address.unwrap().0,
+            "10.0.0.1".parse::<std::net::IpAddr>().unwrap()
+        );
+        assert_eq!(context.properties["seats"], PropertyValue::Number(25.0));
+
+        let err = to_context(&EvaluationContext::default().with_custom_field("remoteAddress", "x"))
+            .unwrap_err();
+        assert_eq!(err.code, EvaluationErrorCode::InvalidContext);
+    }
+}
@@ -0,0 +1,204 @@
+//! Render a client's toggle usage and health as OpenMetrics text.
+//!
+//! [`render`] produces the text exposition format read by Prometheus, to be
+//! served from a metrics endpoint, or appended to the output of another
+//! registry:
+//!
+//! - `unleash_toggle_evaluations_total{toggle, enabled}`: evaluations by
+//!   result, since the client was created.
+//! - `unleash_toggle_variants_total{toggle, variant}`: variants returned.
+//! - `unleash_last_fetch_timestamp_seconds`: when toggles were last loaded.
+//! - `unleash_fetch_errors_total`: failed fetches of toggles.
+//! - `unleash_features`: toggles known from the last load.
+//! - `unleash_metrics_upload_failures_total`: failed metrics uploads.
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Write};
+
+use enum_map::EnumArray;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::client::{CachedFeature, Client};
+use crate::http::HttpClient;
+
+/// The content type to serve [`render`]'s output with.
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Render the client's metrics, ending with the `# EOF` marker.
+pub fn render<F, C>(client: &Client<F, C>) -> String
+where
+    F: EnumArray<CachedFeature> + Clone + Debug + DeserializeOwned + Serialize,
+    C: HttpClient + Default,
+{
+    let totals: BTreeMap<_, _> = client.total_metrics().into_iter().collect();
+    let health = client.health();
+    let mut out = String::new();
+
+    family(
+        &mut out,
+        "unleash_toggle_evaluations",
+        "counter",
+        "Feature toggle evaluations, by result.",
+    );
+    for (name, metrics) in &totals {
+        for (enabled, count) in [("true", metrics.yes), ("false", metrics.no)] {
+            writeln!(
+                out,
+                "unleash_toggle_evaluations_total{{toggle=\"{}\",enabled=\"{enabled}\"}} {count}",
+                escape(name)
+            )
+            .unwrap();
+        }
+    }
+
+    family(
+        &mut out,
+        "unleash_toggle_variants",
+        "counter",
+        "Feature toggle variants returned.",
+    );
+    for (name, metrics) in &totals {
+        let variants: BTreeMap<_, _> = metrics.variants.iter().collect();
+        for (variant, count) in variants {
+            writeln!(
+                out,
+                "unleash_toggle_variants_total{{toggle=\"{}\",variant=\"{}\"}} {count}",
+                escape(name),
+                escape(variant)
+            )
+            .unwrap();
+        }
+    }
+
+    family(
+        &mut out,
+        "unleash_last_fetch_timestamp_seconds",
+        "gauge",
+        "When feature toggles were last loaded.",
+    );
+    if let Some(last_fetch) = health.last_fetch {
+        writeln!(
+            out,
+            "unleash_last_fetch_timestamp_seconds {}.{:03}",
+            last_fetch.timestamp(),
+            last_fetch.timestamp_subsec_millis()
+        )
+        .unwrap();
+    }
+
+    family(
+        &mut out,
+        "unleash_fetch_errors",
+        "counter",
+        "Failed fetches of feature toggles.",
+    );
+    writeln!(out, "unleash_fetch_errors_total {}", health.fetch_errors).unwrap();
+
+    family(
+        &mut out,
+        "unleash_features",
+        "gauge",
+        "Feature toggles known from the last load.",
+    );
+    writeln!(out, "unleash_features {}", health.features).unwrap();
+
+    family(
+        &mut out,
+        "unleash_metrics_upload_failures",
+        "counter",
+        "Failed uploads of metrics to the Unleash server.",
+    );
+    writeln!(
+        out,
+        "unleash_metrics_upload_failures_total {}",
+        health.metrics_upload_failures
+    )
+    .unwrap();
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+    writeln!(out, "# HELP {name} {help}").unwrap();
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use enum_map::Enum;
+    use serde::{Deserialize, Serialize};
+
+    use super::{escape, render};
+    use crate::api::{Feature, Strategy, Variant};
+    use crate::client::ClientBuilder;
+    use crate::context::Context;
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "reqwest")] {
+            use reqwest::Client as HttpClient;
+        } else if #[cfg(feature = "reqwest-11")] {
+            use reqwest_11::Client as HttpClient;
+        }
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    enum UserFeatures {
+        checkout,
+    }
+
+    #[test]
+    fn renders() {
+        let client = ClientBuilder::default()
+            .enable_string_features()
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        let features = || {
+            vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                name: "checkout".into(),
+                variants: Some(vec![Variant {
+                    name: "blue".into(),
+                    weight: 100,
+                    payload: None,
+                    overrides: None,
+                }]),
+                strategies: vec![Strategy {
+                    name: "default".into(),
+                    ..Default::default()
+                }],
+            }]
+        };
+        client.memoize(features()).unwrap();
+        client.get_variant(UserFeatures::checkout, &Context::default());
+        client.is_enabled_str("we\"ird", None, false);
+        // Totals carry over uploaded buckets
+        client.memoize(features()).unwrap();
+        client.is_enabled(UserFeatures::checkout, None, false);
+
+        let text = render(&client);
+        assert!(text.contains(
+            "unleash_toggle_evaluations_total{toggle=\"checkout\",enabled=\"true\"} 2\n"
+        ));
+        assert!(text.contains(
+            "unleash_toggle_evaluations_total{toggle=\"we\\\"ird\",enabled=\"false\"} 1\n"
+        ));
+        assert!(text
+            .contains("unleash_toggle_variants_total{toggle=\"checkout\",variant=\"blue\"} 1\n"));
+        assert!(text.contains("unleash_fetch_errors_total 0\n"));
+        assert!(text.contains("unleash_features 1\n"));
+        assert!(text.contains("\nunleash_last_fetch_timestamp_seconds "));
+        assert!(text.ends_with("# EOF\n"));
+        assert_eq!(escape("a\\b\nc"), "a\\\\b\\nc");
+    }
+}
@@ -0,0 +1,217 @@
+//! OpenTelemetry `feature_flag` span events for evaluations.
+//!
+//! With `ClientBuilder::feature_flag_events`, `Client::is_enabled` and
+//! `Client::get_variant` (and their variations) add an event to the active
+//! span for the selected toggles, following the OpenTelemetry semantic
+//! conventions for feature flags:
+//!
+//! - `feature_flag.key`: the toggle name.
+//! - `feature_flag.provider_name`: [`PROVIDER_NAME`].
+//! - `feature_flag.variant`: the variant name, or `on` / `off` for
+//!   `is_enabled`.
+//!
+//! Nothing is recorded when there is no active span, or it is not recording.
+use std::sync::Arc;
+
+use opentelemetry::trace::get_active_span;
+use opentelemetry::KeyValue;
+
+/// The name of the events.
+pub const EVENT_NAME: &str = "feature_flag";
+
+/// The `feature_flag.provider_name` of the events.
+pub const PROVIDER_NAME: &str = "Unleash";
+
+/// Selects the toggles to record events for, by name.
+pub type ToggleFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// The variant recorded for `is_enabled`.
+pub(crate) fn enabled_variant(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Add a `feature_flag` event to the active span.
+pub(crate) fn record(key: String, variant: &str) {
+    get_active_span(|span| {
+        if !span.is_recording() {
+            return;
+        }
+        span.add_event(
+            EVENT_NAME,
+            vec![
+                KeyValue::new("feature_flag.key", key),
+                KeyValue::new("feature_flag.provider_name", PROVIDER_NAME),
+                KeyValue::new("feature_flag.variant", variant.to_string()),
+            ],
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    use enum_map::Enum;
+    use opentelemetry::trace::{mark_span_as_active, Span, SpanContext, Status};
+    use opentelemetry::KeyValue;
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::{Feature, Strategy};
+    use crate::client::{ClientBuilder, FeatureDefaults, Variant};
+    use crate::context::Context;
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "reqwest")] {
+            use reqwest::Client as HttpClient;
+        } else if #[cfg(feature = "reqwest-11")] {
+            use reqwest_11::Client as HttpClient;
+        }
+    }
+
+    type Events = Arc<Mutex<Vec<(String, Vec<KeyValue>)>>>;
+
+    // A recording span that keeps its events
+    struct TestSpan(Events);
+
+    impl Span for TestSpan {
+        fn add_event_with_timestamp<T>(&mut self, name: T, _: SystemTime, attributes: Vec<KeyValue>)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+            self.0
+                .lock()
+                .unwrap()
+                .push((name.into().into(), attributes));
+        }
+        fn span_context(&self) -> &SpanContext {
+            &SpanContext::NONE
+        }
+        fn is_recording(&self) -> bool {
+            true
+        }
+        fn set_attribute(&mut self, _: KeyValue) {}
+        fn set_status(&mut self, _: Status) {}
+        fn update_name<T>(&mut self, _: T)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+        }
+        fn add_link(&mut self, _: SpanContext, _: Vec<KeyValue>) {}
+        fn end_with_timestamp(&mut self, _: SystemTime) {}
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+    enum UserFeatures {
+        checkout,
+        search,
+        #[serde(rename = "new.banner")]
+        banner,
+    }
+
+    impl FeatureDefaults for UserFeatures {
+        fn default_enabled(&self) -> bool {
+            true
+        }
+
+        fn default_variant(&self) -> Variant {
+            Variant {
+                name: "classic".into(),
+                payload: Default::default(),
+                enabled: true,
+            }
+        }
+    }
+
+    #[test]
+    fn records_selected_toggles() {
+        let client = ClientBuilder::default()
+            .feature_flag_events(|name| name != "search" && name != "banner")
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+        client
+            .memoize(vec![Feature {
+                description: None,
+                enabled: true,
+                created_at: None,
+                name: "checkout".into(),
+                variants: None,
+                strategies: vec![Strategy {
+                    name: "default".into(),
+                    ..Default::default()
+                }],
+            }])
+            .unwrap();
+
+        let events = Events::default();
+        let _guard = mark_span_as_active(TestSpan(events.clone()));
+        client.is_enabled(UserFeatures::checkout, None, false);
+        client.is_enabled(UserFeatures::search, None, false);
+        client.get_variant(UserFeatures::checkout, &Context::default());
+        client.is_enabled(UserFeatures::banner, None, false);
+
+        let events = events.lock().unwrap();
+        let attributes = |i: usize| -> Vec<(String, String)> {
+            events[i]
+                .1
+                .iter()
+                .map(|kv| (kv.key.to_string(), kv.value.to_string()))
+                .collect()
+        };
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].0, "feature_flag");
+        assert_eq!(
+            attributes(0),
+            vec![
+                ("feature_flag.key".into(), "checkout".into()),
+                ("feature_flag.provider_name".into(), "Unleash".into()),
+                ("feature_flag.variant".into(), "on".into()),
+            ]
+        );
+        assert_eq!(attributes(1)[2].1, "disabled");
+        // Renamed features go by their toggle name
+        assert_eq!(attributes(2)[0].1, "new.banner");
+    }
+
+    #[test]
+    fn records_defaults() {
+        let client = ClientBuilder::default()
+            .feature_flag_events(|_| true)
+            .into_client::<UserFeatures, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+            .unwrap();
+
+        let events = Events::default();
+        let _guard = mark_span_as_active(TestSpan(events.clone()));
+        // Not loaded: the defaults are recorded
+        assert!(client.is_enabled_with_defaults(UserFeatures::checkout, None));
+        client.get_variant_with_defaults(UserFeatures::checkout, &Context::default());
+        client.memoize(vec![]).unwrap();
+        // Loaded: recorded once, not again by the inner evaluation
+        assert!(client.is_enabled_with_defaults(UserFeatures::search, None));
+
+        let events = events.lock().unwrap();
+        let variants: Vec<(String, String)> = events
+            .iter()
+            .map(|(_, attributes)| {
+                (
+                    attributes[0].value.to_string(),
+                    attributes[2].value.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            variants,
+            vec![
+                ("checkout".into(), "on".into()),
+                ("checkout".into(), "classic".into()),
+                ("search".into(), "on".into()),
+            ]
+        );
+    }
+}
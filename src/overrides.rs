@@ -0,0 +1,303 @@
+//! Local overrides of toggle states, taking precedence over the server.
+//!
+//! Overrides force a toggle on or off, optionally pinning its variant, in one
+//! process: for incident response, or to try a toggle out locally. They are
+//! consulted by `Client::is_enabled` and `Client::get_variant` (and their
+//! variations) before the cached state, and listed by
+//! [`Overrides::all`] and `Client::explain`.
+//!
+//! Overrides come from three sources, highest precedence first:
+//! - set in code with [`Overrides::set`], via `Client::overrides`;
+//! - a JSON file given to `ClientBuilder::overrides_file`, mapping toggle
+//!   names to overrides, e.g. `{"search": {"enabled": true, "variant":
+//!   "blue"}}`. It is re-read on every poll when its modification time
+//!   changes;
+//! - the `UNLEASH_OVERRIDES` environment variable, read by
+//!   `ClientBuilder::overrides_from_env`, e.g. `search=blue,checkout=off`.
+//!   Each value is `on`, `off` or a variant name (which turns the toggle on).
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::watch::WatchedFile;
+
+/// The environment variable read by `ClientBuilder::overrides_from_env`.
+pub const ENV_VAR: &str = "UNLEASH_OVERRIDES";
+
+/// A forced state for one toggle.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Override {
+    pub enabled: bool,
+    /// The variant `get_variant` returns while enabled. When unset, the
+    /// variant is selected from the toggle's variants as usual.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl Override {
+    /// Force the toggle on or off.
+    pub fn enabled(enabled: bool) -> Self {
+        Self {
+            enabled,
+            variant: None,
+        }
+    }
+
+    /// Force the toggle on, with the variant called `name`.
+    pub fn variant(name: &str) -> Self {
+        Self {
+            enabled: true,
+            variant: Some(name.into()),
+        }
+    }
+}
+
+impl FromStr for Override {
+    type Err = ParseError;
+
+    /// Parse `on`, `off` or a variant name.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "" => Err(ParseError(value.into())),
+            "on" | "true" => Ok(Self::enabled(true)),
+            "off" | "false" => Ok(Self::enabled(false)),
+            variant => Ok(Self::variant(variant)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid override {:?}: expected NAME=on|off|VARIANT",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a comma separated list of `NAME=on|off|VARIANT`, the format of
+/// `UNLEASH_OVERRIDES`.
+pub fn parse_list(list: &str) -> Result<HashMap<String, Override>, ParseError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, value) = entry
+                .split_once('=')
+                .ok_or_else(|| ParseError(entry.into()))?;
+            Ok((name.trim().to_string(), value.parse()?))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Layers {
+    set: HashMap<String, Override>,
+    file: HashMap<String, Override>,
+    env: HashMap<String, Override>,
+}
+
+impl Layers {
+    fn get(&self, name: &str) -> Option<&Override> {
+        self.set
+            .get(name)
+            .or_else(|| self.file.get(name))
+            .or_else(|| self.env.get(name))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.file.is_empty() && self.env.is_empty()
+    }
+}
+
+/// The overrides of a `Client`.
+#[derive(Default)]
+pub struct Overrides {
+    // Lets evaluations skip the lock when there are no overrides.
+    active: AtomicBool,
+    layers: RwLock<Layers>,
+    file: Mutex<Option<WatchedFile>>,
+}
+
+impl Overrides {
+    pub(crate) fn new(env: HashMap<String, Override>, file: Option<PathBuf>) -> Self {
+        let overrides = Self {
+            layers: RwLock::new(Layers {
+                env,
+                ..Default::default()
+            }),
+            file: Mutex::new(file.map(WatchedFile::new)),
+            ..Default::default()
+        };
+        // Account for the overrides from the environment
+        overrides.update(|_| {});
+        overrides.reload();
+        overrides
+    }
+
+    /// Whether there are any overrides.
+    pub(crate) fn active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// The override for the toggle called `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Override> {
+        if !self.active() {
+            return None;
+        }
+        self.layers.read().unwrap().get(name).cloned()
+    }
+
+    /// Every override in effect, by toggle name.
+    pub fn all(&self) -> BTreeMap<String, Override> {
+        let layers = self.layers.read().unwrap();
+        layers
+            .set
+            .keys()
+            .chain(layers.file.keys())
+            .chain(layers.env.keys())
+            .filter_map(|name| Some((name.clone(), layers.get(name)?.clone())))
+            .collect()
+    }
+
+    /// Override the toggle called `name`, until removed.
+    pub fn set(&self, name: &str, value: Override) {
+        self.update(|layers| {
+            layers.set.insert(name.into(), value);
+        });
+    }
+
+    /// Remove an override made with `set`, returning it. Overrides from the
+    /// file or the environment are not affected.
+    pub fn remove(&self, name: &str) -> Option<Override> {
+        let mut removed = None;
+        self.update(|layers| removed = layers.set.remove(name));
+        removed
+    }
+
+    /// Remove every override made with `set`.
+    pub fn clear(&self) {
+        self.update(|layers| layers.set.clear());
+    }
+
+    /// Re-read the overrides file if it changed since it was last read.
+    ///
+    /// Called on every poll by `Client::poll_for_updates`. A file that is
+    /// missing contributes no overrides; one that cannot be parsed is
+    /// reported and the previous overrides from it are kept.
+    pub fn reload(&self) {
+        let mut file = self.file.lock().unwrap();
+        let Some(file) = file.as_mut() else {
+            return;
+        };
+        let overrides = match file.read_if_changed() {
+            Ok(None) => return,
+            Ok(Some(contents)) => match serde_json::from_str(&contents.text) {
+                Ok(overrides) => {
+                    file.loaded(&contents);
+                    overrides
+                }
+                Err(err) => {
+                    warn!("overrides: failed to parse {:?}: {err}", file.path());
+                    return;
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                file.forget();
+                HashMap::new()
+            }
+            Err(err) => {
+                warn!("overrides: failed to read {:?}: {err}", file.path());
+                return;
+            }
+        };
+        debug!(
+            "overrides: loaded {} overrides from {:?}",
+            overrides.len(),
+            file.path()
+        );
+        self.update(|layers| layers.file = overrides);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Layers)) {
+        let mut layers = self.layers.write().unwrap();
+        f(&mut layers);
+        self.active.store(!layers.is_empty(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use maplit::hashmap;
+
+    use super::{parse_list, Override, Overrides};
+
+    #[test]
+    fn parses_lists() {
+        assert_eq!(
+            parse_list(" search=on, checkout = blue,,beta=off").unwrap(),
+            hashmap! {
+                "search".into() => Override::enabled(true),
+                "checkout".into() => Override::variant("blue"),
+                "beta".into() => Override::enabled(false),
+            }
+        );
+        assert!(parse_list("search").is_err());
+        assert!(parse_list("search=").is_err());
+    }
+
+    #[test]
+    fn layers() {
+        let path = std::env::temp_dir().join(format!("overrides-{}.json", uuid::Uuid::new_v4()));
+        let overrides = Overrides::new(
+            hashmap! {
+                "a".into() => Override::enabled(true),
+                "b".into() => Override::enabled(true),
+            },
+            Some(path.clone()),
+        );
+        assert_eq!(overrides.all().len(), 2);
+
+        fs::write(
+            &path,
+            r#"{"b": {"enabled": false}, "c": {"enabled": true, "variant": "blue"}}"#,
+        )
+        .unwrap();
+        overrides.reload();
+        assert_eq!(overrides.get("b"), Some(Override::enabled(false)));
+        assert_eq!(overrides.get("c"), Some(Override::variant("blue")));
+
+        overrides.set("b", Override::variant("green"));
+        assert_eq!(overrides.get("b"), Some(Override::variant("green")));
+        assert_eq!(overrides.remove("b"), Some(Override::variant("green")));
+        assert_eq!(overrides.get("b"), Some(Override::enabled(false)));
+
+        // An unparseable file keeps the previous overrides
+        fs::write(&path, "{").unwrap();
+        // Not every filesystem has a fine grained modification time
+        overrides.file.lock().unwrap().as_mut().unwrap().forget();
+        overrides.reload();
+        assert_eq!(overrides.get("c"), Some(Override::variant("blue")));
+
+        fs::remove_file(&path).unwrap();
+        overrides.reload();
+        assert_eq!(overrides.get("c"), None);
+        assert_eq!(overrides.get("a"), Some(Override::enabled(true)));
+        assert_eq!(overrides.all().len(), 2);
+    }
+}
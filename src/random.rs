@@ -0,0 +1,60 @@
+//! Randomness sources for evaluation.
+//!
+//! Random rollouts and variant selection for contexts without any identifier
+//! draw from the client's random source. It can be replaced via
+//! `ClientBuilder::rng`, e.g. with a [`SeededRandom`] to make tests
+//! deterministic.
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// A source of uniformly distributed random numbers.
+pub trait RandomSource {
+    /// A number in `range`, which must not be empty.
+    fn random_range(&self, range: Range<u32>) -> u32;
+}
+
+/// A shareable random source, as held by the client and compiled strategies.
+pub type SharedRandom = Arc<dyn RandomSource + Send + Sync + 'static>;
+
+/// The thread local generator from the rand crate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadRandom;
+
+impl RandomSource for ThreadRandom {
+    fn random_range(&self, range: Range<u32>) -> u32 {
+        rand::rng().random_range(range)
+    }
+}
+
+/// A deterministic generator: the same seed yields the same sequence.
+#[derive(Debug)]
+pub struct SeededRandom(Mutex<StdRng>);
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self(Mutex::new(StdRng::seed_from_u64(seed)))
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn random_range(&self, range: Range<u32>) -> u32 {
+        self.0.lock().unwrap().random_range(range)
+    }
+}
+
+impl<T> RandomSource for T
+where
+    T: Fn(Range<u32>) -> u32,
+{
+    fn random_range(&self, range: Range<u32>) -> u32 {
+        self(range)
+    }
+}
+
+/// The default random source: the thread local generator.
+pub fn thread() -> SharedRandom {
+    Arc::new(ThreadRandom)
+}
@@ -0,0 +1,205 @@
+//! Redaction of context fields in logs.
+//!
+//! The client logs the context of evaluations at trace level. A
+//! [`RedactionPolicy`], given to `ClientBuilder::redaction`, decides per field
+//! whether its value is shown, replaced by a hash (so log lines can still be
+//! correlated) or hidden. Fields are named as in the Unleash API: `userId`,
+//! `sessionId`, `remoteAddress`, `appName`, `environment`, `currentTime`;
+//! properties go by their own names. The default policy shows everything.
+//!
+//! Spans and events emitted with the `tracing` and `opentelemetry` features
+//! never include the context.
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::io::Cursor;
+
+use murmur3::murmur3_32;
+
+use crate::context::Context;
+
+/// How a field is written to logs.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Redaction {
+    /// Write the value as is.
+    #[default]
+    Show,
+    /// Replace the value with a hash of it, e.g. `#5e1c8a3f`. Hashes are not
+    /// salted: they make values hard to read, not impossible to guess.
+    Hash,
+    /// Replace the value with `<redacted>`.
+    Hide,
+}
+
+/// Which context fields are shown, hashed or hidden in logs.
+#[derive(Clone, Debug, Default)]
+pub struct RedactionPolicy {
+    fields: HashMap<String, Redaction>,
+    others: Redaction,
+}
+
+impl RedactionPolicy {
+    /// Show every field, the default.
+    pub fn show_all() -> Self {
+        Self::default()
+    }
+
+    /// Hide every field not given a rule with [`field`](Self::field).
+    pub fn hide_all() -> Self {
+        Self::default().others(Redaction::Hide)
+    }
+
+    /// Hash every field not given a rule with [`field`](Self::field).
+    pub fn hash_all() -> Self {
+        Self::default().others(Redaction::Hash)
+    }
+
+    /// Set how the field or property called `name` is written.
+    pub fn field(mut self, name: &str, redaction: Redaction) -> Self {
+        self.fields.insert(name.into(), redaction);
+        self
+    }
+
+    /// Set how fields without a rule of their own are written.
+    pub fn others(mut self, redaction: Redaction) -> Self {
+        self.others = redaction;
+        self
+    }
+
+    /// How the field or property called `name` is written.
+    pub fn get(&self, name: &str) -> Redaction {
+        self.fields.get(name).copied().unwrap_or(self.others)
+    }
+
+    /// Wrap `context` to format it with `{:?}` according to the policy.
+    pub fn redact<'a>(&'a self, context: &'a Context) -> Redacted<'a> {
+        Redacted {
+            policy: self,
+            context,
+        }
+    }
+
+    /// Wrap an optional context, as passed to `is_enabled`.
+    pub(crate) fn redact_option<'a>(
+        &'a self,
+        context: Option<&'a Context>,
+    ) -> Option<Redacted<'a>> {
+        context.map(|context| self.redact(context))
+    }
+
+    /// Wrap the value of the field or property called `name` to format it
+    /// with `{:?}` according to the policy, e.g. a value not yet in a context.
+    pub(crate) fn value<'a>(&self, name: &str, value: &'a dyn Debug) -> Value<'a> {
+        Value(self.get(name), value)
+    }
+
+    // Absent fields stay None: redaction does not hide that
+    fn optional<'a, T: Debug>(&self, name: &str, value: &'a Option<T>) -> Option<Value<'a>> {
+        value.as_ref().map(|value| self.value(name, value))
+    }
+}
+
+/// A context formatted according to a [`RedactionPolicy`].
+pub struct Redacted<'a> {
+    policy: &'a RedactionPolicy,
+    context: &'a Context,
+}
+
+impl Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = self.policy;
+        let context = self.context;
+        let mut properties: Vec<_> = context
+            .properties
+            .iter()
+            .map(|(name, value)| (name, policy.value(name, value)))
+            .collect();
+        properties.sort_by_key(|(name, _)| *name);
+        f.debug_struct("Context")
+            .field("user_id", &policy.optional("userId", &context.user_id))
+            .field(
+                "session_id",
+                &policy.optional("sessionId", &context.session_id),
+            )
+            .field(
+                "remote_address",
+                &policy.optional("remoteAddress", &context.remote_address),
+            )
+            .field("properties", &Properties(&properties))
+            .field("app_name", &policy.value("appName", &context.app_name))
+            .field(
+                "environment",
+                &policy.value("environment", &context.environment),
+            )
+            .field(
+                "current_time",
+                &policy.optional("currentTime", &context.current_time),
+            )
+            .finish()
+    }
+}
+
+struct Properties<'a>(&'a [(&'a String, Value<'a>)]);
+
+impl Debug for Properties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, value)| (name, value)))
+            .finish()
+    }
+}
+
+pub(crate) struct Value<'a>(Redaction, &'a dyn Debug);
+
+impl Debug for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Redaction::Show => self.1.fmt(f),
+            Redaction::Hash => {
+                let text = format!("{:?}", self.1);
+                let hash = murmur3_32(&mut Cursor::new(text.as_bytes()), 0).unwrap_or_default();
+                write!(f, "#{hash:08x}")
+            }
+            Redaction::Hide => f.write_str("<redacted>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Redaction, RedactionPolicy};
+    use crate::context::Context;
+
+    #[test]
+    fn redacts() {
+        let context = Context::builder()
+            .user_id("fred")
+            .remote_address("10.0.0.1".parse().unwrap())
+            .property("plan", "pro")
+            .property("email", "fred@example.com")
+            .build();
+
+        let shown = format!("{:?}", RedactionPolicy::default().redact(&context));
+        assert!(shown.contains("\"fred\""));
+        assert!(shown.contains("fred@example.com"));
+
+        let policy = RedactionPolicy::hide_all()
+            .field("userId", Redaction::Hash)
+            .field("plan", Redaction::Show);
+        let redacted = format!("{:?}", policy.redact(&context));
+        assert!(!redacted.contains("fred"), "{redacted}");
+        assert!(!redacted.contains("10.0.0.1"), "{redacted}");
+        assert!(redacted.contains("\"plan\": String(\"pro\")"), "{redacted}");
+        assert!(redacted.contains("\"email\": <redacted>"), "{redacted}");
+        assert!(redacted.contains("session_id: None"), "{redacted}");
+        // Hashes are stable, to correlate log lines
+        let hashed = format!("{:?}", policy.redact(&context));
+        assert_eq!(redacted, hashed);
+        assert!(redacted.contains("user_id: Some(#"), "{redacted}");
+        // Loose values follow the rule of their field
+        assert_eq!(format!("{:?}", policy.value("plan", &"pro")), "\"pro\"");
+        assert_eq!(
+            format!("{:?}", policy.value("remoteAddress", &"10.0.0.x")),
+            "<redacted>"
+        );
+    }
+}
@@ -1,20 +1,22 @@
 // Copyright 2020 Cognite AS
 //! <https://docs.getunleash.io/user_guide/activation_strategy>
+use std::borrow::Cow;
+use std::collections::hash_map::HashMap;
 use std::hash::BuildHasher;
 use std::io::Cursor;
 use std::net::IpAddr;
-use std::{collections::hash_map::HashMap, str::FromStr};
 use std::{collections::hash_set::HashSet, fmt::Display};
 
 use chrono::{DateTime, Utc};
 use ipnet::IpNet;
 use log::{trace, warn};
 use murmur3::murmur3_32;
-use rand::Rng;
 use semver::Version;
 
 use crate::api::{Constraint, ConstraintExpression};
-use crate::context::Context;
+use crate::clock::{self, SharedClock};
+use crate::context::{Context, PropertyValue};
+use crate::random::{self, RandomSource, SharedRandom};
 
 /// Memoise feature state for a strategy.
 pub type Strategy =
@@ -183,6 +185,18 @@ fn _user_id<S: BuildHasher>(
 /// rollout: percentage
 pub fn flexible_rollout<S: BuildHasher>(
     parameters: Option<HashMap<String, String, S>>,
+) -> Evaluate {
+    _flexible_rollout(parameters, random::thread())
+}
+
+/// As `flexible_rollout`, drawing random stickiness from `rng`.
+pub fn flexible_rollout_with(rng: SharedRandom) -> Strategy {
+    Box::new(move |parameters| _flexible_rollout(parameters, rng.clone()))
+}
+
+fn _flexible_rollout<S: BuildHasher>(
+    parameters: Option<HashMap<String, String, S>>,
+    rng: SharedRandom,
 ) -> Evaluate {
     let unwrapped_parameters = if let Some(parameters) = &parameters {
         parameters
@@ -203,13 +217,13 @@ pub fn flexible_rollout<S: BuildHasher>(
                 } else if context.session_id.is_some() {
                     partial_rollout(&group, context.session_id.as_ref(), rollout)
                 } else {
-                    pick_random(rollout as u8)
+                    pick_random(rollout as u8, rng.as_ref())
                 }
             })
         }
         "userId" => _user_id(parameters, "rollout"),
         "sessionId" => _session_id(parameters, "rollout"),
-        "random" => _random(parameters, "rollout"),
+        "random" => _random_from(parameters, "rollout", rng),
         _ => Box::new(|_| false),
     }
 }
@@ -229,15 +243,14 @@ pub fn session_id<S: BuildHasher>(parameters: Option<HashMap<String, String, S>>
 }
 
 /// Perform the is-enabled check for a random rollout of pct.
-fn pick_random(pct: u8) -> bool {
+fn pick_random(pct: u8, rng: &(dyn RandomSource + Send + Sync)) -> bool {
     match pct {
         0 => false,
         100 => true,
         pct => {
-            let mut rng = rand::rng();
             // generates 0's but not 100's.
             let picked = rng.random_range(0..100);
-            pct > picked
+            pct as u32 > picked
         }
     }
 }
@@ -247,6 +260,14 @@ fn pick_random(pct: u8) -> bool {
 pub fn _random<S: BuildHasher>(
     parameters: Option<HashMap<String, String, S>>,
     rollout_key: &str,
+) -> Evaluate {
+    _random_from(parameters, rollout_key, random::thread())
+}
+
+fn _random_from<S: BuildHasher>(
+    parameters: Option<HashMap<String, String, S>>,
+    rollout_key: &str,
+    rng: SharedRandom,
 ) -> Evaluate {
     let mut pct = 0;
     if let Some(parameters) = parameters {
@@ -256,7 +277,7 @@ pub fn _random<S: BuildHasher>(
             }
         }
     }
-    Box::new(move |_: &Context| -> bool { pick_random(pct) })
+    Box::new(move |_: &Context| -> bool { pick_random(pct, rng.as_ref()) })
 }
 
 /// <https://docs.getunleash.io/user_guide/activation_strategy#gradualrolloutrandom-deprecated-from-v4---use-gradual-rollout-instead>
@@ -265,6 +286,11 @@ pub fn random<S: BuildHasher>(parameters: Option<HashMap<String, String, S>>) ->
     _random(parameters, "percentage")
 }
 
+/// As `random`, drawing from `rng`.
+pub fn random_with(rng: SharedRandom) -> Strategy {
+    Box::new(move |parameters| _random_from(parameters, "percentage", rng.clone()))
+}
+
 /// <https://docs.getunleash.io/user_guide/activation_strategy#ips>
 /// IPs: 1.2.3.4,AB::CD::::EF,1.2/8
 pub fn remote_address<S: BuildHasher>(parameters: Option<HashMap<String, String, S>>) -> Evaluate {
@@ -325,66 +351,188 @@ fn lower_case_if<S: Display>(case_insensitive: bool) -> impl Fn(S) -> String {
     }
 }
 
-fn handle_parsable_op<T, C, F>(getter: F, compare_fn: C) -> Evaluate
+/// A context value that constraints can compare: strings are parsed as
+/// needed, typed property values are used as is.
+trait ConstraintValue {
+    fn as_str(&self) -> Cow<'_, str>;
+    fn as_number(&self) -> Option<f64>;
+    fn as_date(&self) -> Option<DateTime<Utc>>;
+    fn as_semver(&self) -> Option<Cow<'_, Version>>;
+}
+
+impl ConstraintValue for String {
+    fn as_str(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        self.parse().ok()
+    }
+
+    fn as_date(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(self)
+            .ok()
+            .map(|date| date.to_utc())
+    }
+
+    fn as_semver(&self) -> Option<Cow<'_, Version>> {
+        self.parse().ok().map(Cow::Owned)
+    }
+}
+
+impl ConstraintValue for PropertyValue {
+    fn as_str(&self) -> Cow<'_, str> {
+        PropertyValue::as_str(self)
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        PropertyValue::as_number(self)
+    }
+
+    fn as_date(&self) -> Option<DateTime<Utc>> {
+        PropertyValue::as_date(self)
+    }
+
+    fn as_semver(&self) -> Option<Cow<'_, Version>> {
+        PropertyValue::as_semver(self)
+    }
+}
+
+impl ConstraintValue for IpAddr {
+    fn as_str(&self) -> Cow<'_, str> {
+        Cow::Owned(self.to_string())
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        None
+    }
+
+    fn as_date(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn as_semver(&self) -> Option<Cow<'_, Version>> {
+        None
+    }
+}
+
+fn handle_number_op<V, C, F>(getter: F, compare_fn: C) -> Evaluate
 where
-    T: FromStr,
-    C: Fn(T) -> bool + Clone + Sync + Send + 'static,
-    F: Fn(&Context) -> Option<&String> + Clone + Sync + Send + 'static,
+    V: ConstraintValue + ?Sized + 'static,
+    C: Fn(f64) -> bool + Clone + Sync + Send + 'static,
+    F: Fn(&Context) -> Option<&V> + Clone + Sync + Send + 'static,
 {
     Box::new(move |context: &Context| {
         getter(context)
-            .and_then(|v| v.parse::<T>().ok())
+            .and_then(|v| v.as_number())
             .map(&compare_fn)
             .unwrap_or(false)
     })
 }
 
-fn handle_str_op<T, C, F>(
+fn handle_semver_op<V, C, F>(getter: F, compare_fn: C) -> Evaluate
+where
+    V: ConstraintValue + ?Sized + 'static,
+    C: Fn(&Version) -> bool + Clone + Sync + Send + 'static,
+    F: Fn(&Context) -> Option<&V> + Clone + Sync + Send + 'static,
+{
+    Box::new(move |context: &Context| {
+        getter(context)
+            .and_then(|v| v.as_semver())
+            .map(|v| compare_fn(&v))
+            .unwrap_or(false)
+    })
+}
+
+fn handle_date_op<V, C, F>(getter: F, compare_fn: C) -> Evaluate
+where
+    V: ConstraintValue + ?Sized + 'static,
+    C: Fn(DateTime<Utc>) -> bool + Clone + Sync + Send + 'static,
+    F: Fn(&Context) -> Option<&V> + Clone + Sync + Send + 'static,
+{
+    Box::new(move |context: &Context| {
+        getter(context)
+            .and_then(|v| v.as_date())
+            .map(&compare_fn)
+            .unwrap_or(false)
+    })
+}
+
+fn handle_str_op<V, C, F>(
     values: Vec<String>,
     getter: F,
     case_insensitive: bool,
     compare_fn: C,
 ) -> Evaluate
 where
-    T: Display,
-    C: Fn(&String, &String) -> bool + Clone + Sync + Send + 'static,
-    F: Fn(&Context) -> Option<&T> + Clone + Sync + Send + 'static,
+    V: ConstraintValue + ?Sized + 'static,
+    C: Fn(&str, &str) -> bool + Clone + Sync + Send + 'static,
+    F: Fn(&Context) -> Option<&V> + Clone + Sync + Send + 'static,
 {
     let as_vec: Vec<String> = values.iter().map(lower_case_if(case_insensitive)).collect();
     Box::new(move |context: &Context| {
         getter(context)
-            .map(lower_case_if(case_insensitive))
-            .map(|v| as_vec.iter().any(|entry| compare_fn(&v, entry)))
+            .map(|v| {
+                let v = v.as_str();
+                let v = if case_insensitive {
+                    Cow::Owned(v.to_lowercase())
+                } else {
+                    v
+                };
+                as_vec.iter().any(|entry| compare_fn(&v, entry))
+            })
             .unwrap_or(false)
     })
 }
 
 /// returns true if the strategy should be delegated to, false to disable
-fn _compile_constraint_string<F, B>(
+fn _compile_constraint_string<V, F, B>(
     expression: ConstraintExpression,
     apply_invert: B,
     case_insensitive: bool,
     getter: F,
 ) -> Evaluate
 where
-    F: Fn(&Context) -> Option<&String> + Clone + Sync + Send + 'static,
+    V: ConstraintValue + ?Sized + 'static,
+    F: Fn(&Context) -> Option<&V> + Clone + Sync + Send + 'static,
     B: Fn(bool) -> bool + Sync + Send + Clone + 'static,
 {
     let compiled_fn: Box<dyn Evaluator + Send + Sync + 'static> = match expression {
         ConstraintExpression::In { values } => {
-            let as_set: HashSet<String> = values.iter().cloned().collect();
-
-            Box::new(move |context: &Context| {
-                getter(context).map(|v| as_set.contains(v)).unwrap_or(false)
-            })
+            if case_insensitive {
+                let as_set: HashSet<String> =
+                    values.iter().map(lower_case_if(case_insensitive)).collect();
+                Box::new(move |context: &Context| {
+                    getter(context)
+                        .map(|v| as_set.contains(&v.as_str().to_lowercase()))
+                        .unwrap_or(false)
+                })
+            } else {
+                let as_set: HashSet<String> = values.iter().cloned().collect();
+                Box::new(move |context: &Context| {
+                    getter(context)
+                        .map(|v| as_set.contains(v.as_str().as_ref()))
+                        .unwrap_or(false)
+                })
+            }
         }
         ConstraintExpression::NotIn { values } => {
             if values.is_empty() {
                 Box::new(|_| true)
+            } else if case_insensitive {
+                let as_set: HashSet<String> =
+                    values.iter().map(lower_case_if(case_insensitive)).collect();
+                Box::new(move |context: &Context| {
+                    getter(context)
+                        .map(|v| !as_set.contains(&v.as_str().to_lowercase()))
+                        .unwrap_or(true)
+                })
             } else {
                 let as_set: HashSet<String> = values.iter().cloned().collect();
                 Box::new(move |context: &Context| {
-                    getter(context).map(|v| !as_set.contains(v)).unwrap_or(true)
+                    getter(context)
+                        .map(|v| !as_set.contains(v.as_str().as_ref()))
+                        .unwrap_or(true)
                 })
             }
         }
@@ -403,30 +551,16 @@ where
                 v.ends_with(entry)
             })
         }
-        ConstraintExpression::NumEq { value } => {
-            handle_parsable_op(getter, move |v: f64| v == value)
-        }
-        ConstraintExpression::NumGT { value } => {
-            handle_parsable_op(getter, move |v: f64| v > value)
-        }
-        ConstraintExpression::NumGTE { value } => {
-            handle_parsable_op(getter, move |v: f64| v >= value)
-        }
-        ConstraintExpression::NumLT { value } => {
-            handle_parsable_op(getter, move |v: f64| v < value)
-        }
-        ConstraintExpression::NumLTE { value } => {
-            handle_parsable_op(getter, move |v: f64| v <= value)
-        }
-        ConstraintExpression::SemverEq { value } => {
-            handle_parsable_op(getter, move |v: Version| v == value)
-        }
-        ConstraintExpression::SemverGT { value } => {
-            handle_parsable_op(getter, move |v: Version| v > value)
-        }
-        ConstraintExpression::SemverLT { value } => {
-            handle_parsable_op(getter, move |v: Version| v < value)
-        }
+        ConstraintExpression::NumEq { value } => handle_number_op(getter, move |v| v == value),
+        ConstraintExpression::NumGT { value } => handle_number_op(getter, move |v| v > value),
+        ConstraintExpression::NumGTE { value } => handle_number_op(getter, move |v| v >= value),
+        ConstraintExpression::NumLT { value } => handle_number_op(getter, move |v| v < value),
+        ConstraintExpression::NumLTE { value } => handle_number_op(getter, move |v| v <= value),
+        ConstraintExpression::SemverEq { value } => handle_semver_op(getter, move |v| *v == value),
+        ConstraintExpression::SemverGT { value } => handle_semver_op(getter, move |v| *v > value),
+        ConstraintExpression::SemverLT { value } => handle_semver_op(getter, move |v| *v < value),
+        ConstraintExpression::DateAfter { value } => handle_date_op(getter, move |v| v > value),
+        ConstraintExpression::DateBefore { value } => handle_date_op(getter, move |v| v < value),
         _ => Box::new(|_| false),
     };
 
@@ -440,15 +574,15 @@ fn _compile_constraint_date<F, B>(
     getter: F,
 ) -> Evaluate
 where
-    F: Fn(&Context) -> Option<&DateTime<Utc>> + Clone + Sync + Send + 'static,
+    F: Fn(&Context) -> Option<DateTime<Utc>> + Clone + Sync + Send + 'static,
     B: Fn(bool) -> bool + Sync + Send + Clone + 'static,
 {
     let compiled_fn: Box<dyn Evaluator + Send + Sync + 'static> = match expression {
         ConstraintExpression::DateAfter { value } => {
-            Box::new(move |context: &Context| getter(context).map(|v| *v > value).unwrap_or(false))
+            Box::new(move |context: &Context| getter(context).map(|v| v > value).unwrap_or(false))
         }
         ConstraintExpression::DateBefore { value } => {
-            Box::new(move |context: &Context| getter(context).map(|v| *v < value).unwrap_or(false))
+            Box::new(move |context: &Context| getter(context).map(|v| v < value).unwrap_or(false))
         }
         _ => Box::new(|_| false),
     };
@@ -550,7 +684,7 @@ fn _apply_invert(inverted: bool) -> impl Fn(bool) -> bool + Clone {
     }
 }
 
-fn _compile_constraints(constraints: Vec<Constraint>) -> Vec<Evaluate> {
+fn _compile_constraints(constraints: Vec<Constraint>, clock: &SharedClock) -> Vec<Evaluate> {
     constraints
         .into_iter()
         .map(|constraint| {
@@ -593,9 +727,13 @@ fn _compile_constraints(constraints: Vec<Constraint>) -> Vec<Evaluate> {
                     case_insensitive,
                     |context| context.user_id.as_ref(),
                 ),
-                "currentTime" => _compile_constraint_date(expression, apply_invert, |context| {
-                    context.current_time.as_ref()
-                }),
+                "currentTime" => {
+                    // Callers rarely set the time; evaluate against now.
+                    let clock = clock.clone();
+                    _compile_constraint_date(expression, apply_invert, move |context| {
+                        Some(context.current_time.unwrap_or_else(|| clock.now()))
+                    })
+                }
                 _ => _compile_constraint_string(
                     expression,
                     apply_invert,
@@ -609,10 +747,26 @@ fn _compile_constraints(constraints: Vec<Constraint>) -> Vec<Evaluate> {
 
 /// This function is a strategy decorator which compiles to nothing when
 /// there are no constraints, or to a constraint evaluating test if there are.
+///
+/// Date constraints on contexts without a `current_time` are evaluated against
+/// the system clock.
 pub fn constrain<S: Fn(Option<HashMap<String, String>>) -> Evaluate + Sync + Send + 'static>(
     constraints: Option<Vec<Constraint>>,
     strategy: &S,
     parameters: Option<HashMap<String, String>>,
+) -> Evaluate {
+    constrain_with_clock(constraints, strategy, parameters, &clock::system())
+}
+
+/// As `constrain`, but date constraints on contexts without a `current_time`
+/// are evaluated against `clock`.
+pub fn constrain_with_clock<
+    S: Fn(Option<HashMap<String, String>>) -> Evaluate + Sync + Send + 'static,
+>(
+    constraints: Option<Vec<Constraint>>,
+    strategy: &S,
+    parameters: Option<HashMap<String, String>>,
+    clock: &SharedClock,
 ) -> Evaluate {
     let compiled_strategy = strategy(parameters);
     match constraints {
@@ -626,7 +780,7 @@ pub fn constrain<S: Fn(Option<HashMap<String, String>>) -> Evaluate + Sync + Sen
                 compiled_strategy
             } else {
                 trace!("constrain: compiling constraints list {constraints:?}");
-                let constraints = _compile_constraints(constraints);
+                let constraints = _compile_constraints(constraints, clock);
                 // Create a closure that will evaluate against the context.
                 Box::new(move |context| {
                     // Check every constraint; if all match, permit
@@ -650,6 +804,8 @@ fn _parse_ip(ip: &str) -> Result<IpNet, std::net::AddrParseError> {
 #[cfg(test)]
 mod tests {
     use std::default::Default;
+    use std::ops::Range;
+    use std::sync::Arc;
     use std::{collections::hash_map::HashMap, str::FromStr};
 
     use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
@@ -657,7 +813,9 @@ mod tests {
     use semver::Version;
 
     use crate::api::{Constraint, ConstraintExpression};
+    use crate::clock::SharedClock;
     use crate::context::{Context, IPAddress};
+    use crate::random::{SeededRandom, SharedRandom};
 
     fn parse_ip(addr: &str) -> Option<IPAddress> {
         Some(IPAddress(addr.parse().unwrap()))
@@ -944,6 +1102,94 @@ mod tests {
         )(&context));
     }
 
+    #[test]
+    fn test_constrain_with_case_insensitive_in_constraints() {
+        let context = Context {
+            user_id: Some("Fred".into()),
+            properties: hashmap! {
+                "team".into() => "Platform".into()
+            },
+            remote_address: parse_ip("2001:db8::1"),
+            ..Default::default()
+        };
+        let constraint = |context_name: &str, expression, case_insensitive| {
+            super::constrain(
+                Some(vec![Constraint {
+                    context_name: context_name.into(),
+                    expression,
+                    case_insensitive,
+                    ..default_constraint()
+                }]),
+                &super::default,
+                None,
+            )(&context)
+        };
+        let values = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+
+        // In is exact unless marked case insensitive
+        assert!(!constraint(
+            "userId",
+            ConstraintExpression::In {
+                values: values(&["fred"])
+            },
+            false
+        ));
+        assert!(constraint(
+            "userId",
+            ConstraintExpression::In {
+                values: values(&["FRED", "barney"])
+            },
+            true
+        ));
+        // NotIn likewise
+        assert!(constraint(
+            "userId",
+            ConstraintExpression::NotIn {
+                values: values(&["fred"])
+            },
+            false
+        ));
+        assert!(!constraint(
+            "userId",
+            ConstraintExpression::NotIn {
+                values: values(&["fred"])
+            },
+            true
+        ));
+        // Properties
+        assert!(constraint(
+            "team",
+            ConstraintExpression::In {
+                values: values(&["platform"])
+            },
+            true
+        ));
+        assert!(!constraint(
+            "team",
+            ConstraintExpression::NotIn {
+                values: values(&["PLATFORM"])
+            },
+            true
+        ));
+        // Addresses are parsed, so hex digits match in either case
+        for case_insensitive in [false, true] {
+            assert!(constraint(
+                "remoteAddress",
+                ConstraintExpression::In {
+                    values: values(&["2001:DB8::1"])
+                },
+                case_insensitive
+            ));
+            assert!(!constraint(
+                "remoteAddress",
+                ConstraintExpression::NotIn {
+                    values: values(&["2001:DB8::/32"])
+                },
+                case_insensitive
+            ));
+        }
+    }
+
     #[test]
     fn test_constrain_with_date_constraints() {
         let now = Utc::now();
@@ -1028,7 +1274,63 @@ mod tests {
             None
         )(&context));
 
-        // date comparison only works for currentTime
+        // without a current_time in the context, now is used
+        let context = Context::default();
+        assert!(super::constrain(
+            Some(vec![Constraint {
+                context_name: "currentTime".into(),
+                expression: ConstraintExpression::DateAfter {
+                    value: Utc::now() - TimeDelta::seconds(30)
+                },
+                ..default_constraint()
+            }]),
+            &super::default,
+            None
+        )(&context));
+        assert!(!super::constrain(
+            Some(vec![Constraint {
+                context_name: "currentTime".into(),
+                expression: ConstraintExpression::DateBefore {
+                    value: Utc::now() - TimeDelta::seconds(30)
+                },
+                ..default_constraint()
+            }]),
+            &super::default,
+            None
+        )(&context));
+
+        // and now can come from an injected clock
+        let fixed = DateTime::<FixedOffset>::parse_from_rfc3339("2024-07-18T17:18:25.844Z")
+            .unwrap()
+            .to_utc();
+        let clock: SharedClock = Arc::new(move || fixed);
+        let constraint = || {
+            Some(vec![Constraint {
+                context_name: "currentTime".into(),
+                expression: ConstraintExpression::DateBefore {
+                    value: fixed + TimeDelta::seconds(1),
+                },
+                ..default_constraint()
+            }])
+        };
+        assert!(super::constrain_with_clock(
+            constraint(),
+            &super::default,
+            None,
+            &clock
+        )(&context));
+        assert!(!super::constrain(constraint(), &super::default, None)(
+            &context
+        ));
+
+        let context = Context {
+            current_time: DateTime::<FixedOffset>::parse_from_rfc3339("2024-07-18T17:18:25.844Z")
+                .ok()
+                .map(|date| date.to_utc()),
+            ..Default::default()
+        };
+
+        // other fields compare only when they hold a date
         assert!(!super::constrain(
             Some(vec![Constraint {
                 context_name: "environment".into(),
@@ -1038,6 +1340,61 @@ mod tests {
             &super::default,
             None
         )(&context));
+
+        let context = Context {
+            user_id: Some("2024-07-18T17:18:25Z".into()),
+            properties: hashmap! {
+                "signupDate".into() => "2024-07-18T19:18:25+02:00".into(),
+                "name".into() => "fred".into(),
+            },
+            ..Default::default()
+        };
+        let cutoff = DateTime::<FixedOffset>::parse_from_rfc3339("2024-07-18T17:00:00Z")
+            .unwrap()
+            .to_utc();
+        let constraint = |context_name: &str, expression, inverted| {
+            super::constrain(
+                Some(vec![Constraint {
+                    context_name: context_name.into(),
+                    expression,
+                    inverted,
+                    ..default_constraint()
+                }]),
+                &super::default,
+                None,
+            )(&context)
+        };
+        assert!(constraint(
+            "signupDate",
+            ConstraintExpression::DateAfter { value: cutoff },
+            false
+        ));
+        assert!(!constraint(
+            "signupDate",
+            ConstraintExpression::DateBefore { value: cutoff },
+            false
+        ));
+        assert!(constraint(
+            "signupDate",
+            ConstraintExpression::DateBefore { value: cutoff },
+            true
+        ));
+        assert!(constraint(
+            "userId",
+            ConstraintExpression::DateAfter { value: cutoff },
+            false
+        ));
+        // unparseable or missing values never match
+        assert!(!constraint(
+            "name",
+            ConstraintExpression::DateAfter { value: cutoff },
+            false
+        ));
+        assert!(!constraint(
+            "missing",
+            ConstraintExpression::DateBefore { value: cutoff },
+            false
+        ));
     }
 
     #[test]
@@ -1335,6 +1692,114 @@ mod tests {
         )(&context));
     }
 
+    #[test]
+    fn test_constrain_builtin_fields_compare_as_values() {
+        let context = Context {
+            user_id: Some("42".into()),
+            session_id: Some("7".into()),
+            app_name: "1.2.3".into(),
+            environment: "2.0.0".into(),
+            ..Default::default()
+        };
+        let constraint = |context_name: &str, expression| {
+            super::constrain(
+                Some(vec![Constraint {
+                    context_name: context_name.into(),
+                    expression,
+                    ..default_constraint()
+                }]),
+                &super::default,
+                None,
+            )(&context)
+        };
+        assert!(constraint(
+            "userId",
+            ConstraintExpression::NumGT { value: 41.0 }
+        ));
+        assert!(constraint(
+            "sessionId",
+            ConstraintExpression::NumLTE { value: 7.0 }
+        ));
+        assert!(constraint(
+            "appName",
+            ConstraintExpression::SemverLT {
+                value: Version::parse("1.10.0").unwrap()
+            }
+        ));
+        assert!(constraint(
+            "environment",
+            ConstraintExpression::SemverEq {
+                value: Version::parse("2.0.0").unwrap()
+            }
+        ));
+        // non-numeric values do not compare
+        assert!(!constraint(
+            "appName",
+            ConstraintExpression::NumGT { value: 0.0 }
+        ));
+    }
+
+    #[test]
+    fn test_constrain_typed_properties() {
+        let context = Context::builder()
+            .property("seats", 25)
+            .property("beta", true)
+            .property("version", Version::parse("2.1.0").unwrap())
+            .property(
+                "signup",
+                DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                    .unwrap()
+                    .to_utc(),
+            )
+            .build();
+        let constraint = |context_name: &str, expression| {
+            super::constrain(
+                Some(vec![Constraint {
+                    context_name: context_name.into(),
+                    expression,
+                    ..default_constraint()
+                }]),
+                &super::default,
+                None,
+            )(&context)
+        };
+        assert!(constraint(
+            "seats",
+            ConstraintExpression::NumGTE { value: 25.0 }
+        ));
+        assert!(constraint(
+            "seats",
+            ConstraintExpression::In {
+                values: vec!["25".into()]
+            }
+        ));
+        assert!(constraint(
+            "beta",
+            ConstraintExpression::In {
+                values: vec!["true".into()]
+            }
+        ));
+        assert!(constraint(
+            "version",
+            ConstraintExpression::SemverGT {
+                value: Version::parse("2.0.0").unwrap()
+            }
+        ));
+        assert!(constraint(
+            "signup",
+            ConstraintExpression::DateBefore {
+                value: DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z")
+                    .unwrap()
+                    .to_utc()
+            }
+        ));
+        // typed values only convert where it makes sense
+        assert!(!constraint(
+            "beta",
+            ConstraintExpression::NumGT { value: 0.0 }
+        ));
+    }
+
     #[test]
     fn test_constrain_with_num_constraints() {
         let context = Context {
@@ -1618,6 +2083,38 @@ mod tests {
         assert!(super::random(Some(params))(&c));
     }
 
+    #[test]
+    fn test_random_with() {
+        let params = || -> Option<HashMap<String, String>> {
+            Some(hashmap! {
+                "percentage".into() => "50".into()
+            })
+        };
+        let c: Context = Default::default();
+        // A source that always picks the top or bottom of the range
+        let high: SharedRandom = Arc::new(|range: Range<u32>| range.end - 1);
+        let low: SharedRandom = Arc::new(|range: Range<u32>| range.start);
+        assert!(!super::random_with(high.clone())(params())(&c));
+        assert!(super::random_with(low.clone())(params())(&c));
+
+        let params = || -> Option<HashMap<String, String>> {
+            Some(hashmap! {
+                "rollout".into() => "50".into(),
+                "stickiness".into() => "default".into(),
+            })
+        };
+        assert!(!super::flexible_rollout_with(high)(params())(&c));
+        assert!(super::flexible_rollout_with(low)(params())(&c));
+
+        // Seeded sources repeat their sequences
+        let sample = |seed| -> Vec<bool> {
+            let evaluate =
+                super::flexible_rollout_with(Arc::new(SeededRandom::new(seed)))(params());
+            (0..64).map(|_| evaluate(&c)).collect()
+        };
+        assert_eq!(sample(7), sample(7));
+    }
+
     #[test]
     fn test_remote_address() {
         let params: HashMap<String, String> = hashmap! {
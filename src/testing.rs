@@ -0,0 +1,268 @@
+//! Test doubles for code that evaluates features.
+//!
+//! [`FakeClient`] answers the same evaluation calls as `Client`, through the
+//! [`Toggles`] trait, from states set by the test instead of fetched toggles.
+//! It has no HTTP client type parameter, records no metrics and needs no
+//! `memoize` call:
+//!
+//! ```
+//! use serde::Serialize;
+//! use unleash_api_client::testing::FakeClient;
+//! use unleash_api_client::{Context, Toggles};
+//!
+//! #[derive(Debug, Serialize)]
+//! enum Features {
+//!     Checkout,
+//! }
+//!
+//! fn checkout_label(toggles: &impl Toggles<Features>, context: &Context) -> String {
+//!     toggles.get_variant(Features::Checkout, context).name
+//! }
+//!
+//! let fake = FakeClient::new();
+//! fake.feature(Features::Checkout)
+//!     .when(|context| context.user_id.as_deref() == Some("fred"))
+//!     .variant("blue");
+//! let fred = Context {
+//!     user_id: Some("fred".into()),
+//!     ..Default::default()
+//! };
+//! assert_eq!(checkout_label(&fake, &fred), "blue");
+//! assert_eq!(checkout_label(&fake, &Context::default()), "disabled");
+//! ```
+//!
+//! To test the whole client instead, including polling, metrics and error
+//! handling, point it at a [`MockServer`].
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::client::{ToggleName, Toggles, Variant};
+use crate::context::Context;
+
+mod server;
+pub use server::{Endpoint, MockServer, Request};
+
+/// Whether an override applies to a context.
+pub type Predicate = Arc<dyn Fn(&Context) -> bool + Send + Sync>;
+
+struct Rule {
+    feature: String,
+    when: Option<Predicate>,
+    enabled: bool,
+    variant: Option<Variant>,
+}
+
+impl Rule {
+    fn applies(&self, feature_name: &str, context: &Context) -> bool {
+        self.feature == feature_name && self.when.as_ref().map_or(true, |when| when(context))
+    }
+}
+
+/// An in-memory stand-in for `Client`.
+///
+/// Features are set with [`feature`](Self::feature) or
+/// [`feature_str`](Self::feature_str); when several overrides apply to a
+/// context the most recently set one wins. Features that were never set
+/// evaluate to the caller's default, with the disabled variant. Enum features
+/// are named by their serde name, the toggle name `Client` maps them to, so an
+/// override set by name applies to the enum variant of that name too.
+pub struct FakeClient<F> {
+    rules: Mutex<Vec<Rule>>,
+    _features: PhantomData<fn(F)>,
+}
+
+impl<F> Default for FakeClient<F> {
+    fn default() -> Self {
+        Self {
+            rules: Mutex::default(),
+            _features: PhantomData,
+        }
+    }
+}
+
+impl<F: Debug + Serialize> FakeClient<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a [`FakeOverride`] of `feature_enum`.
+    pub fn feature(&self, feature_enum: F) -> FakeOverride<'_, F> {
+        self.feature_str(&ToggleName(&feature_enum).to_string())
+    }
+
+    /// Start a [`FakeOverride`] of the feature called `feature_name`.
+    pub fn feature_str(&self, feature_name: &str) -> FakeOverride<'_, F> {
+        FakeOverride {
+            client: self,
+            feature: feature_name.into(),
+            when: None,
+        }
+    }
+
+    /// Forget all overrides.
+    pub fn reset(&self) {
+        self.rules.lock().unwrap().clear();
+    }
+
+    fn evaluate(&self, feature_name: &str, context: Option<&Context>, default: bool) -> bool {
+        let default_context = Context::default();
+        let context = context.unwrap_or(&default_context);
+        let rules = self.rules.lock().unwrap();
+        rules
+            .iter()
+            .rev()
+            .find(|rule| rule.applies(feature_name, context))
+            .map_or(default, |rule| rule.enabled)
+    }
+
+    fn variant(&self, feature_name: &str, context: &Context) -> Variant {
+        let rules = self.rules.lock().unwrap();
+        rules
+            .iter()
+            .rev()
+            .find(|rule| rule.applies(feature_name, context))
+            .and_then(|rule| rule.variant.clone())
+            .unwrap_or_else(Variant::disabled)
+    }
+}
+
+impl<F: Debug + Serialize> Toggles<F> for FakeClient<F> {
+    fn is_enabled(&self, feature_enum: F, context: Option<&Context>, default: bool) -> bool {
+        self.evaluate(&ToggleName(&feature_enum).to_string(), context, default)
+    }
+
+    fn is_enabled_str(&self, feature_name: &str, context: Option<&Context>, default: bool) -> bool {
+        self.evaluate(feature_name, context, default)
+    }
+
+    fn get_variant(&self, feature_enum: F, context: &Context) -> Variant {
+        self.variant(&ToggleName(&feature_enum).to_string(), context)
+    }
+
+    fn get_variant_str(&self, feature_name: &str, context: &Context) -> Variant {
+        self.variant(feature_name, context)
+    }
+}
+
+/// A fake override being set on a `FakeClient`, applied by
+/// [`enabled`](Self::enabled), [`variant`](Self::variant) or
+/// [`variant_with`](Self::variant_with). Unrelated to the client overrides of
+/// `overrides::Override`.
+#[must_use = "a fake override does nothing until enabled, variant or variant_with is called"]
+pub struct FakeOverride<'a, F> {
+    client: &'a FakeClient<F>,
+    feature: String,
+    when: Option<Predicate>,
+}
+
+impl<F> FakeOverride<'_, F> {
+    /// Only apply to contexts matching `predicate`.
+    pub fn when(mut self, predicate: impl Fn(&Context) -> bool + Send + Sync + 'static) -> Self {
+        self.when = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Turn the feature on or off, with the disabled variant.
+    pub fn enabled(self, enabled: bool) {
+        self.apply(enabled, None);
+    }
+
+    /// Turn the feature on, selecting the variant called `name`.
+    pub fn variant(self, name: &str) {
+        self.variant_with(Variant {
+            name: name.into(),
+            payload: HashMap::new(),
+            enabled: true,
+        });
+    }
+
+    /// Turn the feature on, selecting `variant`.
+    pub fn variant_with(self, variant: Variant) {
+        self.apply(true, Some(variant));
+    }
+
+    fn apply(self, enabled: bool, variant: Option<Variant>) {
+        self.client.rules.lock().unwrap().push(Rule {
+            feature: self.feature,
+            when: self.when,
+            enabled,
+            variant,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde::Serialize;
+
+    use super::FakeClient;
+    use crate::client::{Toggles, Variant};
+    use crate::context::Context;
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Serialize)]
+    enum UserFeatures {
+        checkout,
+        search,
+        #[serde(rename = "new.banner")]
+        banner,
+    }
+
+    fn user(id: &str) -> Context {
+        Context {
+            user_id: Some(id.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overrides() {
+        let fake = FakeClient::new();
+        assert!(!fake.is_enabled(UserFeatures::search, None, false));
+        assert!(fake.is_enabled(UserFeatures::search, None, true));
+
+        fake.feature(UserFeatures::search).enabled(true);
+        fake.feature(UserFeatures::search)
+            .when(|c| c.user_id.as_deref() == Some("fred"))
+            .enabled(false);
+        assert!(fake.is_enabled(UserFeatures::search, None, false));
+        assert!(fake.is_enabled(UserFeatures::search, Some(&user("bob")), false));
+        assert!(!fake.is_enabled(UserFeatures::search, Some(&user("fred")), true));
+        assert!(fake.is_enabled_str("search", Some(&user("bob")), false));
+        assert_eq!(
+            fake.get_variant(UserFeatures::search, &user("bob")),
+            Variant::disabled()
+        );
+
+        fake.feature_str("checkout")
+            .when(|c| c.user_id.is_some())
+            .variant_with(Variant {
+                name: "blue".into(),
+                payload: hashmap! {"type".into() => "string".into()},
+                enabled: true,
+            });
+        let variant = fake.get_variant(UserFeatures::checkout, &user("bob"));
+        assert_eq!(variant.name, "blue");
+        assert_eq!(variant.payload["type"], "string");
+        assert!(fake.is_enabled(UserFeatures::checkout, Some(&user("bob")), false));
+        assert!(!fake.is_enabled(UserFeatures::checkout, None, false));
+        assert_eq!(
+            fake.get_variant_str("checkout", &Context::default()),
+            Variant::disabled()
+        );
+
+        // Renamed variants go by their toggle name
+        fake.feature_str("new.banner").enabled(true);
+        assert!(fake.is_enabled(UserFeatures::banner, None, false));
+        fake.feature(UserFeatures::banner).variant("red");
+        assert_eq!(fake.get_variant_str("new.banner", &user("bob")).name, "red");
+
+        fake.reset();
+        assert!(!fake.is_enabled(UserFeatures::search, None, false));
+    }
+}
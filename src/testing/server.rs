@@ -0,0 +1,326 @@
+//! An in-process mock of the Unleash client API.
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use crate::api::{Feature, Features, Metrics, Registration};
+
+/// The client API endpoints the mock serves.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Endpoint {
+    /// `GET /client/features`
+    Features,
+    /// `POST /client/register`
+    Register,
+    /// `POST /client/metrics`
+    Metrics,
+}
+
+impl Endpoint {
+    fn route(method: &str, path: &str) -> Option<Self> {
+        let path = path.split('?').next().unwrap_or_default();
+        match (method, path.trim_end_matches('/')) {
+            ("GET", p) if p.ends_with("/client/features") => Some(Self::Features),
+            ("POST", p) if p.ends_with("/client/register") => Some(Self::Register),
+            ("POST", p) if p.ends_with("/client/metrics") => Some(Self::Metrics),
+            _ => None,
+        }
+    }
+}
+
+/// A request received by the mock.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lower case.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    features: Vec<Feature>,
+    // Bumped whenever the features change, to derive the ETag
+    revision: u64,
+    latency: Duration,
+    queued: HashMap<Endpoint, VecDeque<u16>>,
+    requests: Vec<Request>,
+}
+
+/// An Unleash server for tests, listening on a local port.
+///
+/// It serves the features set with [`set_features`](Self::set_features),
+/// with an ETag, answering 304 to a matching `If-None-Match`, and accepts
+/// registrations and metrics, recording every request. Errors, 304s and
+/// latency can be injected to exercise the client's handling of them. The
+/// server stops when dropped.
+///
+/// ```no_run
+/// # use unleash_api_client::testing::MockServer;
+/// let server = MockServer::start()?;
+/// server.set_features(vec![]);
+/// // ClientBuilder::default().into_client(&server.url(), ...)
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server on an unused local port.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let stopping = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let state = state.clone();
+            let stopping = stopping.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopping.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let state = state.clone();
+                    let stopping = stopping.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = serve(stream, &state, &stopping) {
+                            log::debug!("mock server: connection closed: {err}");
+                        }
+                    });
+                }
+            })
+        };
+        Ok(Self {
+            address,
+            state,
+            stopping,
+            thread: Some(thread),
+        })
+    }
+
+    /// The API URL to build a client with.
+    pub fn url(&self) -> String {
+        format!("http://{}/api", self.address)
+    }
+
+    /// Set the features served from now on.
+    pub fn set_features(&self, features: Vec<Feature>) {
+        let mut state = self.state.lock().unwrap();
+        state.features = features;
+        state.revision += 1;
+    }
+
+    /// Delay every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Answer the next request to `endpoint` with `status` and an empty body
+    /// instead of handling it. Queued statuses are used in order.
+    pub fn respond_next(&self, endpoint: Endpoint, status: u16) {
+        let mut state = self.state.lock().unwrap();
+        state.queued.entry(endpoint).or_default().push_back(status);
+    }
+
+    /// Answer the next features request with 304 Not Modified, whatever its
+    /// `If-None-Match`.
+    pub fn not_modified_next(&self) {
+        self.respond_next(Endpoint::Features, 304);
+    }
+
+    /// Every request received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The number of requests received so far to `endpoint`.
+    pub fn count(&self, endpoint: Endpoint) -> usize {
+        self.requests()
+            .iter()
+            .filter(|r| Endpoint::route(&r.method, &r.path) == Some(endpoint))
+            .count()
+    }
+
+    /// The registrations received so far.
+    pub fn registrations(&self) -> Vec<Registration> {
+        self.bodies(Endpoint::Register)
+    }
+
+    /// The metrics received so far.
+    pub fn metrics(&self) -> Vec<Metrics> {
+        self.bodies(Endpoint::Metrics)
+    }
+
+    fn bodies<T: DeserializeOwned>(&self, endpoint: Endpoint) -> Vec<T> {
+        self.requests()
+            .iter()
+            .filter(|r| Endpoint::route(&r.method, &r.path) == Some(endpoint))
+            .filter_map(|r| serde_json::from_slice(&r.body).ok())
+            .collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        // Wake the accept loop up so it sees it is stopping
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Serve requests on a connection until it is closed or the server stops.
+fn serve(stream: TcpStream, state: &Mutex<State>, stopping: &AtomicBool) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_millis(50)))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        // Wait for the next request, checking for shutdown
+        match reader.fill_buf() {
+            Ok([]) => return Ok(()),
+            Ok(_) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                if stopping.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+        let request = read_request(&mut reader)?;
+        let (response, latency) = {
+            let mut state = state.lock().unwrap();
+            let response = respond(&mut state, &request);
+            state.requests.push(request);
+            (response, state.latency)
+        };
+        thread::sleep(latency);
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\ncontent-length: {}\r\n",
+            response.status,
+            reason(response.status),
+            response.body.len()
+        )?;
+        for (name, value) in &response.headers {
+            write!(writer, "{name}: {value}\r\n")?;
+        }
+        writer.write_all(b"\r\n")?;
+        writer.write_all(&response.body)?;
+        writer.flush()?;
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| invalid("no method"))?
+        .to_string();
+    let path = parts.next().ok_or_else(|| invalid("no path"))?.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+    let length = match headers.get("content-length") {
+        Some(length) => length.parse().map_err(|_| invalid("bad content-length"))?,
+        None => 0,
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+fn respond(state: &mut State, request: &Request) -> Response {
+    let Some(endpoint) = Endpoint::route(&request.method, &request.path) else {
+        return Response::status(404);
+    };
+    if let Some(status) = state
+        .queued
+        .get_mut(&endpoint)
+        .and_then(VecDeque::pop_front)
+    {
+        return Response::status(status);
+    }
+    match endpoint {
+        Endpoint::Features => {
+            let etag = format!("\"{}\"", state.revision);
+            if request.headers.get("if-none-match") == Some(&etag) {
+                return Response::status(304);
+            }
+            let features = Features {
+                version: 1,
+                features: state.features.clone(),
+            };
+            Response {
+                status: 200,
+                headers: vec![("content-type", "application/json".into()), ("etag", etag)],
+                body: serde_json::to_vec(&features).unwrap(),
+            }
+        }
+        Endpoint::Register | Endpoint::Metrics => Response::status(202),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        304 => "Not Modified",
+        400..=499 => "Client Error",
+        500..=599 => "Server Error",
+        _ => "",
+    }
+}
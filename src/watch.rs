@@ -0,0 +1,53 @@
+//! Files re-read when their modification time changes.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub(crate) struct WatchedFile {
+    path: PathBuf,
+    // Of the contents last loaded
+    modified: Option<SystemTime>,
+}
+
+/// The contents of a watched file, as read.
+pub(crate) struct Contents {
+    pub(crate) text: String,
+    modified: SystemTime,
+}
+
+impl WatchedFile {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the file if it was modified since the contents last passed to
+    /// `loaded`.
+    pub(crate) fn read_if_changed(&self) -> io::Result<Option<Contents>> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+        if Some(modified) == self.modified {
+            return Ok(None);
+        }
+        Ok(Some(Contents {
+            text: fs::read_to_string(&self.path)?,
+            modified,
+        }))
+    }
+
+    /// Record that `contents` were loaded, so they are not read again.
+    pub(crate) fn loaded(&mut self, contents: &Contents) {
+        self.modified = Some(contents.modified);
+    }
+
+    /// Forget what was loaded, so the file is read again.
+    pub(crate) fn forget(&mut self) {
+        self.modified = None;
+    }
+}
@@ -5,6 +5,7 @@
 mod tests {
     use std::env;
     use std::fs;
+    use std::path::Path;
 
     use enum_map::Enum;
     use serde::{Deserialize, Serialize};
@@ -83,6 +84,25 @@ mod tests {
     #[test]
     fn test_client_specification() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>
     {
+        let current_exe_path = env::current_exe().unwrap();
+        let mut exe_dir = current_exe_path.parent().unwrap();
+        if exe_dir.ends_with("deps") {
+            exe_dir = exe_dir.parent().unwrap();
+        }
+        run_suites(&exe_dir.join("../../client-specification/specifications/"))
+    }
+
+    /// Suites in the client specification format covering behaviour not yet
+    /// in the upstream specification.
+    #[test]
+    fn test_local_specification() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        run_suites(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/specifications/"))
+    }
+
+    fn run_suites(
+        spec_dir: &Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         cfg_if::cfg_if! {
             if #[cfg(feature = "reqwest")] {
                 use reqwest::Client as HttpClient;
@@ -98,13 +118,6 @@ mod tests {
             .with_module_level("tracing::span", log::LevelFilter::Off)
             .with_module_level("tracing::span::active", log::LevelFilter::Off)
             .init();
-        let current_exe_path = env::current_exe().unwrap();
-        let mut exe_dir = current_exe_path.parent().unwrap();
-        if exe_dir.ends_with("deps") {
-            exe_dir = exe_dir.parent().unwrap();
-        }
-        let spec_dir = exe_dir.join("../../client-specification/specifications/");
-
         log::info!("Loading tests from {}", spec_dir.display());
         let index = fs::read(spec_dir.join("index.json"))?;
         let suite_names: Vec<String> = serde_json::from_slice(&index)?;
@@ -0,0 +1,124 @@
+#![cfg(feature = "derive")]
+use std::collections::HashMap;
+
+use enum_map::Enum;
+use unleash_api_client::api::{Feature, Strategy};
+use unleash_api_client::client::Variant;
+use unleash_api_client::{ClientBuilder, FeatureDefaults, FeatureEnum};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "reqwest")] {
+        use reqwest::Client as HttpClient;
+    } else if #[cfg(feature = "reqwest-11")] {
+        use reqwest_11::Client as HttpClient;
+    }
+}
+
+#[derive(FeatureEnum, PartialEq)]
+enum Features {
+    #[unleash(name = "checkout.new-flow")]
+    NewCheckout,
+    #[unleash(default = true)]
+    Search,
+    #[unleash(name = "beta-banner", default = true, default_variant = "blue")]
+    BetaBanner,
+}
+
+#[test]
+fn names_and_defaults() {
+    assert_eq!(Features::NewCheckout.name(), "checkout.new-flow");
+    assert_eq!(Features::Search.name(), "Search");
+    assert_eq!(format!("{:?}", Features::BetaBanner), "beta-banner");
+    assert!(!Features::NewCheckout.default_enabled());
+    assert!(Features::Search.default_enabled());
+    assert!(Features::BetaBanner.default_enabled());
+    assert_eq!(Features::Search.default_variant(), Variant::disabled());
+    assert_eq!(Features::BetaBanner.default_variant().name, "blue");
+    assert!(Features::BetaBanner.default_variant().enabled);
+}
+
+#[test]
+fn enum_and_serde() {
+    assert_eq!(Features::LENGTH, 3);
+    for i in 0..Features::LENGTH {
+        assert_eq!(Features::from_usize(i).into_usize(), i);
+    }
+    assert_eq!(
+        serde_plain::to_string(&Features::NewCheckout).unwrap(),
+        "checkout.new-flow"
+    );
+    assert_eq!(
+        serde_json::to_string(&Features::BetaBanner).unwrap(),
+        r#""beta-banner""#
+    );
+    assert_eq!(
+        serde_plain::from_str::<Features>("checkout.new-flow").unwrap(),
+        Features::NewCheckout
+    );
+    assert_eq!(
+        serde_json::from_str::<Features>(r#""Search""#).unwrap(),
+        Features::Search
+    );
+    assert!(serde_plain::from_str::<Features>("NewCheckout").is_err());
+}
+
+#[test]
+fn renamed_toggles_are_memoized() {
+    let client = ClientBuilder::default()
+        .into_client::<Features, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+        .unwrap();
+    let feature = |name: &str| Feature {
+        description: None,
+        enabled: true,
+        created_at: None,
+        variants: None,
+        name: name.into(),
+        strategies: vec![Strategy {
+            name: "default".into(),
+            parameters: Some(HashMap::new()),
+            ..Default::default()
+        }],
+    };
+    client
+        .memoize(vec![feature("checkout.new-flow"), feature("NewCheckout")])
+        .unwrap();
+    assert!(client.is_enabled(Features::NewCheckout, None, false));
+    assert!(!client.is_enabled(Features::Search, None, false));
+}
+
+#[test]
+fn defaults_apply_to_unknown_toggles() {
+    let client = ClientBuilder::default()
+        .into_client::<Features, HttpClient>("http://127.0.0.1:1234/", "foo", "test", None)
+        .unwrap();
+    let context = Default::default();
+    // Nothing fetched yet
+    assert!(client.is_enabled_with_defaults(Features::Search, None));
+    assert!(!client.is_enabled_with_defaults(Features::NewCheckout, None));
+    assert_eq!(
+        client
+            .get_variant_with_defaults(Features::BetaBanner, &context)
+            .name,
+        "blue"
+    );
+
+    client
+        .memoize(vec![Feature {
+            description: None,
+            enabled: false,
+            created_at: None,
+            variants: None,
+            name: "beta-banner".into(),
+            strategies: vec![],
+        }])
+        .unwrap();
+    // Known toggles ignore the defaults
+    assert!(!client.is_enabled_with_defaults(Features::BetaBanner, None));
+    assert_eq!(
+        client.get_variant_with_defaults(Features::BetaBanner, &context),
+        Variant::disabled()
+    );
+    // Unknown ones still use them
+    assert!(client.is_enabled_with_defaults(Features::Search, None));
+    assert!(!client.is_enabled_with_defaults(Features::NewCheckout, None));
+}
@@ -0,0 +1,211 @@
+//! The poll and metrics loop against an in-process mock Unleash server.
+#![cfg(all(
+    feature = "test-support",
+    any(feature = "reqwest", feature = "reqwest-11")
+))]
+
+use std::time::{Duration, Instant};
+
+use enum_map::Enum;
+use futures_timer::Delay;
+use serde::{Deserialize, Serialize};
+
+use unleash_api_client::api::{Feature, Strategy};
+use unleash_api_client::testing::{Endpoint, MockServer};
+use unleash_api_client::ClientBuilder;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "reqwest")] {
+        use reqwest::Client as HttpClient;
+    } else if #[cfg(feature = "reqwest-11")] {
+        use reqwest_11::Client as HttpClient;
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Deserialize, Serialize, Enum, Clone)]
+enum UserFeatures {
+    default,
+}
+
+fn default_feature(enabled: bool) -> Feature {
+    Feature {
+        description: None,
+        enabled,
+        created_at: None,
+        variants: None,
+        name: "default".into(),
+        strategies: vec![Strategy {
+            name: "default".into(),
+            ..Default::default()
+        }],
+    }
+}
+
+/// Wait until `condition` holds, failing the test after a generous deadline.
+async fn eventually(what: &str, condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {what}");
+        Delay::new(Duration::from_millis(5)).await;
+    }
+}
+
+#[tokio::test]
+async fn poll_and_metrics() {
+    let server = MockServer::start().unwrap();
+    server.set_features(vec![default_feature(true)]);
+    let client = ClientBuilder::default()
+        .interval(50)
+        .into_client::<UserFeatures, HttpClient>(&server.url(), "app", "instance", None)
+        .unwrap();
+    client.register().await.unwrap();
+    // explain records no metrics, unlike is_enabled
+    let enabled = || client.explain(UserFeatures::default, None).enabled;
+    futures::future::join(client.poll_for_updates(), async {
+        eventually("the first fetch", enabled).await;
+        assert!(client.is_enabled(UserFeatures::default, None, false));
+
+        // Errors and 304s leave the toggles as they were
+        server.respond_next(Endpoint::Features, 500);
+        server.not_modified_next();
+        server.set_features(vec![default_feature(false)]);
+        eventually("the failed fetch", || client.health().fetch_errors == 1).await;
+        assert!(client.is_enabled(UserFeatures::default, None, false));
+        eventually("the changed toggles", || !enabled()).await;
+        assert!(!client.is_enabled(UserFeatures::default, None, true));
+        eventually("the metrics of the enabled toggle", || {
+            let yes: u64 = server
+                .metrics()
+                .iter()
+                .filter_map(|m| m.bucket.toggles.get("default"))
+                .map(|t| t.yes)
+                .sum();
+            yes == 2
+        })
+        .await;
+        client.stop_poll().await;
+    })
+    .await;
+
+    let health = client.health();
+    assert_eq!(health.fetch_errors, 1);
+    assert_eq!(health.features, 1);
+    assert_eq!(health.metrics_upload_failures, 0);
+    let registrations = server.registrations();
+    assert_eq!(registrations.len(), 1);
+    assert_eq!(registrations[0].app_name, "app");
+    assert!(server.count(Endpoint::Features) >= 4);
+    let request = &server.requests()[0];
+    assert_eq!(request.headers["unleash-appname"], "app");
+}
+
+#[tokio::test]
+async fn latency_and_failed_metrics() {
+    let server = MockServer::start().unwrap();
+    server.set_features(vec![default_feature(true)]);
+    server.set_latency(Duration::from_millis(100));
+    server.respond_next(Endpoint::Register, 500);
+    server.respond_next(Endpoint::Metrics, 500);
+    let client = ClientBuilder::default()
+        .interval(50)
+        .into_client::<UserFeatures, HttpClient>(&server.url(), "app", "instance", None)
+        .unwrap();
+    assert!(client.register().await.is_err());
+    futures::future::join(client.poll_for_updates(), async {
+        // The first fetch is still waiting on the server
+        assert!(client.cached_state().is_none());
+        eventually("the first fetch", || client.cached_state().is_some()).await;
+        assert!(client.is_enabled(UserFeatures::default, None, false));
+        // The first upload fails, the second succeeds
+        eventually("two metrics uploads", || {
+            server.count(Endpoint::Metrics) >= 2 && client.health().metrics_upload_failures > 0
+        })
+        .await;
+        client.stop_poll().await;
+    })
+    .await;
+
+    assert_eq!(client.health().metrics_upload_failures, 1);
+    assert_eq!(client.health().fetch_errors, 0);
+}
+
+#[tokio::test]
+async fn not_modified_polls_upload_metrics() {
+    let server = MockServer::start().unwrap();
+    server.set_features(vec![default_feature(true)]);
+    let client = ClientBuilder::default()
+        .interval(50)
+        .into_client::<UserFeatures, HttpClient>(&server.url(), "app", "instance", None)
+        .unwrap();
+    let last_fetch = || client.health().last_fetch;
+    futures::future::join(client.poll_for_updates(), async {
+        eventually("the first fetch", || last_fetch().is_some()).await;
+        let fetched = last_fetch();
+        // Every later poll is answered with a 304
+        for _ in 0..1000 {
+            server.not_modified_next();
+        }
+        assert!(client.is_enabled(UserFeatures::default, None, false));
+        eventually("the metrics of the evaluation", || {
+            server
+                .metrics()
+                .iter()
+                .filter_map(|m| m.bucket.toggles.get("default"))
+                .any(|t| t.yes == 1)
+        })
+        .await;
+        assert!(last_fetch() > fetched);
+        client.stop_poll().await;
+    })
+    .await;
+
+    assert!(client.is_enabled(UserFeatures::default, None, false));
+    assert_eq!(client.health().fetch_errors, 0);
+}
+
+#[tokio::test]
+async fn etag_revalidation() {
+    let server = MockServer::start().unwrap();
+    server.set_features(vec![default_feature(true)]);
+    let client = ClientBuilder::default()
+        .interval(50)
+        .into_client::<UserFeatures, HttpClient>(&server.url(), "app", "instance", None)
+        .unwrap();
+    // The If-None-Match of each features request
+    let etags = || -> Vec<Option<String>> {
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.method == "GET")
+            .map(|r| r.headers.get("if-none-match").cloned())
+            .collect()
+    };
+    let enabled = || client.explain(UserFeatures::default, None).enabled;
+    futures::future::join(client.poll_for_updates(), async {
+        eventually("revalidating fetches", || etags().len() >= 3).await;
+        let etags = etags();
+        assert_eq!(etags[0], None);
+        let first = etags[1]
+            .clone()
+            .expect("If-None-Match after the first fetch");
+        assert_eq!(etags[2].as_ref(), Some(&first));
+        assert!(enabled());
+
+        // A new revision is fetched in full, and has a new ETag
+        server.set_features(vec![default_feature(false)]);
+        eventually("the changed toggles", || !enabled()).await;
+        eventually("revalidation of the changed toggles", || {
+            server.requests().iter().any(|r| {
+                r.headers
+                    .get("if-none-match")
+                    .is_some_and(|etag| *etag != first)
+            })
+        })
+        .await;
+        client.stop_poll().await;
+    })
+    .await;
+
+    assert_eq!(client.health().fetch_errors, 0);
+}